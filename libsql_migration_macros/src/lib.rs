@@ -0,0 +1,83 @@
+//! Procedural macros for `libsql_migration`. Use them through the `embed` feature
+//! of that crate rather than depending on this crate directly.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{LitStr, parse_macro_input};
+
+/// Embeds the `.sql` files of a migrations directory into the binary.
+///
+/// The path is relative to the `Cargo.toml` of the crate invoking the macro.
+/// Expands to a `libsql_migration::embed::EmbeddedMigrations`.
+#[proc_macro]
+pub fn embed_migrations(input: TokenStream) -> TokenStream {
+    let path = parse_macro_input!(input as LitStr);
+
+    let root = match std::env::var_os("CARGO_MANIFEST_DIR") {
+        Some(manifest_dir) => PathBuf::from(manifest_dir).join(path.value()),
+        None => PathBuf::from(path.value()),
+    };
+
+    if !root.is_dir() {
+        return syn::Error::new(
+            path.span(),
+            format!("{} is not a migrations directory", root.display()),
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let files = match sql_files(&root) {
+        Ok(files) => files,
+        Err(e) => {
+            return syn::Error::new(
+                path.span(),
+                format!("Unable to read {}: {}", root.display(), e),
+            )
+            .to_compile_error()
+            .into();
+        }
+    };
+
+    let entries = files.iter().map(|file| {
+        let id = file
+            .strip_prefix(&root)
+            .unwrap_or(file)
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let absolute = file.to_string_lossy();
+
+        quote! { (#id, include_str!(#absolute)) }
+    });
+
+    quote! {
+        ::libsql_migration::embed::EmbeddedMigrations::new(&[#(#entries),*])
+    }
+    .into()
+}
+
+/// Every `.sql` file below `root`, in no particular order.
+fn sql_files(root: &Path) -> Result<Vec<PathBuf>, io::Error> {
+    let mut file_paths: Vec<PathBuf> = vec![];
+    let mut path_to_visit: Vec<PathBuf> = vec![root.to_path_buf()];
+
+    while let Some(current_path) = path_to_visit.pop() {
+        if current_path.is_dir() {
+            for entry in fs::read_dir(current_path)? {
+                path_to_visit.push(entry?.path());
+            }
+        } else if current_path.is_file() && current_path.extension().is_some_and(|ext| ext == "sql")
+        {
+            file_paths.push(current_path);
+        }
+    }
+
+    Ok(file_paths)
+}
@@ -1,5 +1,8 @@
-CREATE TABLE IF NOT EXISTS libsql_migrations (
+CREATE TABLE IF NOT EXISTS {table} (
   id TEXT PRIMARY KEY,
   status BOOLEAN default false,
-  exec_time DATE 
+  exec_time DATE
+);
+CREATE TABLE IF NOT EXISTS {version_table} (
+  version INTEGER NOT NULL
 );
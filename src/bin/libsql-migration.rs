@@ -0,0 +1,388 @@
+//! Command-line interface to apply and inspect migrations of a `dir` or `remote`
+//! source. Built with the `cli` feature.
+
+use std::{error::Error, path::PathBuf, process::ExitCode};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use libsql::{Builder, Connection};
+use libsql_migration::{
+    dir::{
+        CreateMigrationOptions, DirSource, MigrationPrefix, create_migration_with_options,
+        rollback_with_options,
+    },
+    migrator::{MigrationSource, Migrator},
+    remote::{RemoteSource, VerifyingKey},
+    util::{
+        MigrateOptions, Migration, MigrationTable, NestedDirMode, OrderingPolicy, OrderingStrategy,
+    },
+};
+use reqwest::header::{HeaderName, HeaderValue};
+
+type CliError = Box<dyn Error + Send + Sync>;
+
+#[derive(Parser)]
+#[command(
+    name = "libsql-migration",
+    version,
+    about = "Apply and inspect libsql migrations"
+)]
+struct Cli {
+    /// Local database path or libsql URL (libsql://, http(s)://, ws(s)://).
+    #[arg(long, short = 'd', env = "LIBSQL_DATABASE_URL", global = true)]
+    database: Option<String>,
+
+    /// Auth token for a remote database.
+    #[arg(long, env = "LIBSQL_AUTH_TOKEN", hide_env_values = true, global = true)]
+    auth_token: Option<String>,
+
+    /// Table tracking the applied migrations.
+    #[arg(long, default_value = "libsql_migrations", global = true)]
+    table: String,
+
+    /// How migration ids are ordered.
+    #[arg(long, value_enum, default_value_t = Order::Lexicographic, global = true)]
+    order: Order,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Args)]
+struct SourceArgs {
+    /// Directory containing the `.sql` migration files.
+    #[arg(long, default_value = "./migrations")]
+    dir: PathBuf,
+
+    /// URL of a remote migration manifest, used instead of `--dir`.
+    #[arg(long)]
+    remote: Option<String>,
+
+    /// Header sent with every remote request, as `Name: value`. Repeatable.
+    #[arg(long = "header", value_name = "HEADER", requires = "remote")]
+    headers: Vec<String>,
+
+    /// Bearer token sent with every remote request.
+    #[arg(
+        long,
+        env = "LIBSQL_MIGRATION_REMOTE_TOKEN",
+        hide_env_values = true,
+        requires = "remote"
+    )]
+    remote_token: Option<String>,
+
+    /// Hex encoded ed25519 public key the remote manifest must be signed with.
+    #[arg(long, requires = "remote")]
+    manifest_key: Option<String>,
+
+    /// Identify files in subdirectories of `--dir` by their file name alone.
+    #[arg(long, conflicts_with = "remote")]
+    flat: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Apply every pending migration.
+    Up {
+        #[command(flatten)]
+        source: SourceArgs,
+
+        /// List the migrations that would be applied without executing them.
+        #[arg(long)]
+        dry_run: bool,
+
+        /// How to handle pending migrations sorting before an applied one.
+        #[arg(long, value_enum, default_value_t = Ordering::Allow)]
+        ordering: Ordering,
+    },
+    /// Show the state of every migration.
+    Status {
+        #[command(flatten)]
+        source: SourceArgs,
+    },
+    /// Check that applied migrations were not modified.
+    Verify {
+        #[command(flatten)]
+        source: SourceArgs,
+    },
+    /// Create a new, empty migration file named `<timestamp>_<name>.sql`.
+    ///
+    /// With a database, refuses to create a migration sorting before an already
+    /// applied one.
+    New {
+        /// Description of the migration, used in the file name.
+        name: String,
+
+        /// Directory to create the file in.
+        #[arg(long, default_value = "./migrations")]
+        dir: PathBuf,
+
+        /// Create a `.up.sql`/`.down.sql` pair.
+        #[arg(long)]
+        reversible: bool,
+
+        /// Prefix the name with the next sequence number instead of a timestamp.
+        #[arg(long)]
+        sequence: bool,
+    },
+    /// Revert applied migrations using their stored down scripts.
+    #[command(group = clap::ArgGroup::new("target").required(true))]
+    Rollback {
+        /// Revert every migration applied after this id.
+        #[arg(long, group = "target")]
+        to: Option<String>,
+
+        /// Revert every applied migration.
+        #[arg(long, group = "target")]
+        all: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Ordering {
+    /// Fail without applying anything.
+    Strict,
+    /// Apply them.
+    Allow,
+    /// Apply them and print a warning.
+    Warn,
+}
+
+impl From<Ordering> for OrderingPolicy {
+    fn from(ordering: Ordering) -> Self {
+        match ordering {
+            Ordering::Strict => OrderingPolicy::Strict,
+            Ordering::Allow => OrderingPolicy::Allow,
+            Ordering::Warn => OrderingPolicy::Warn,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Order {
+    /// By file name, character by character.
+    Lexicographic,
+    /// By the number the file name starts with.
+    Numeric,
+    /// By the dotted version the file name starts with, like `v1.2`.
+    Version,
+    /// By the timestamp the file name starts with.
+    Timestamp,
+}
+
+impl From<Order> for OrderingStrategy {
+    fn from(order: Order) -> Self {
+        match order {
+            Order::Lexicographic => OrderingStrategy::Lexicographic,
+            Order::Numeric => OrderingStrategy::NumericPrefix,
+            Order::Version => OrderingStrategy::VersionPrefix,
+            Order::Timestamp => OrderingStrategy::TimestampPrefix,
+        }
+    }
+}
+
+fn parse_key(hex: &str) -> Result<VerifyingKey, CliError> {
+    let bytes: Vec<u8> = (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+        })
+        .collect::<Option<_>>()
+        .ok_or("--manifest-key is not hex encoded")?;
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| "--manifest-key is not 32 bytes long")?;
+
+    Ok(VerifyingKey::from_bytes(&bytes)?)
+}
+
+/// The source selected on the command line.
+enum CliSource {
+    Dir(DirSource),
+    Remote(Box<RemoteSource>),
+}
+
+impl CliSource {
+    fn new(args: SourceArgs) -> Result<Self, CliError> {
+        Ok(match args.remote {
+            Some(url) => {
+                let mut source = RemoteSource::new(url);
+                for header in &args.headers {
+                    let (name, value) = header
+                        .split_once(':')
+                        .ok_or_else(|| format!("{:?} is not a `Name: value` header", header))?;
+                    source = source.with_header(
+                        HeaderName::try_from(name.trim())?,
+                        HeaderValue::try_from(value.trim())?,
+                    );
+                }
+                if let Some(token) = args.remote_token {
+                    source = source.with_bearer_token(token);
+                }
+                if let Some(key) = &args.manifest_key {
+                    source = source.with_manifest_key(parse_key(key)?);
+                }
+                CliSource::Remote(Box::new(source))
+            }
+            None => {
+                let nested_dirs = if args.flat {
+                    NestedDirMode::Flat
+                } else {
+                    NestedDirMode::Namespaced
+                };
+                CliSource::Dir(DirSource::new(args.dir).with_nested_dirs(nested_dirs))
+            }
+        })
+    }
+}
+
+impl MigrationSource for CliSource {
+    type Error = CliError;
+
+    async fn migrations(&self) -> Result<Vec<Migration>, Self::Error> {
+        match self {
+            CliSource::Dir(source) => Ok(source.migrations().await?),
+            CliSource::Remote(source) => Ok(source.migrations().await?),
+        }
+    }
+
+    async fn pending_migrations(&self, applied: &[String]) -> Result<Vec<Migration>, Self::Error> {
+        match self {
+            CliSource::Dir(source) => Ok(source.pending_migrations(applied).await?),
+            CliSource::Remote(source) => Ok(source.pending_migrations(applied).await?),
+        }
+    }
+}
+
+async fn connect(database: Option<&str>, auth_token: Option<&str>) -> Result<Connection, CliError> {
+    let database = database.ok_or("no database given, use --database or LIBSQL_DATABASE_URL")?;
+
+    let is_remote = ["libsql://", "http://", "https://", "ws://", "wss://"]
+        .iter()
+        .any(|scheme| database.starts_with(scheme));
+
+    let db = if is_remote {
+        Builder::new_remote(
+            database.to_string(),
+            auth_token.unwrap_or_default().to_string(),
+        )
+        .build()
+        .await?
+    } else {
+        Builder::new_local(database).build().await?
+    };
+
+    Ok(db.connect()?)
+}
+
+async fn run(cli: Cli) -> Result<(), CliError> {
+    let table = MigrationTable::new(cli.table);
+    let strategy = OrderingStrategy::from(cli.order);
+    let options = MigrateOptions {
+        table: table.clone(),
+        strategy,
+        ..Default::default()
+    };
+    let database = cli.database.as_deref();
+    let auth_token = cli.auth_token.as_deref();
+
+    match cli.command {
+        Command::New {
+            name,
+            dir,
+            reversible,
+            sequence,
+        } => {
+            let conn = match database {
+                Some(_) => Some(connect(database, auth_token).await?),
+                None => None,
+            };
+            let prefix = if sequence {
+                MigrationPrefix::Sequence
+            } else {
+                MigrationPrefix::Timestamp
+            };
+            let options = CreateMigrationOptions {
+                prefix,
+                reversible,
+                migrate: options,
+            };
+
+            for path in create_migration_with_options(conn.as_ref(), dir, &name, &options).await? {
+                println!("Created {}", path.display());
+            }
+        }
+        Command::Up {
+            source,
+            dry_run,
+            ordering,
+        } => {
+            let conn = connect(database, auth_token).await?;
+            let migrator = Migrator::new(CliSource::new(source)?).with_options(MigrateOptions {
+                dry_run,
+                ordering: ordering.into(),
+                ..options
+            });
+
+            let report = migrator.migrate(&conn).await?;
+            for id in &report.out_of_order {
+                eprintln!("Warning: {} sorts before an already applied migration", id);
+            }
+            let verb = if dry_run { "Would apply" } else { "Applied" };
+            for id in &report.applied {
+                match report.durations.get(id) {
+                    Some(duration) => println!("{} {} ({:?})", verb, id, duration),
+                    None => println!("{} {}", verb, id),
+                }
+            }
+            println!(
+                "{} applied, {} already applied, in {:?}",
+                report.applied.len(),
+                report.skipped.len(),
+                report.total_duration
+            );
+        }
+        Command::Status { source } => {
+            let conn = connect(database, auth_token).await?;
+            let migrator = Migrator::new(CliSource::new(source)?).with_options(options);
+
+            for status in migrator.status(&conn).await? {
+                println!(
+                    "{:<18} {} {}",
+                    format!("{:?}", status.state),
+                    status.id,
+                    status.applied_at.unwrap_or_default()
+                );
+            }
+        }
+        Command::Verify { source } => {
+            let conn = connect(database, auth_token).await?;
+            Migrator::new(CliSource::new(source)?)
+                .with_options(options)
+                .verify(&conn)
+                .await?;
+            println!("Applied migrations match their sources");
+        }
+        Command::Rollback { to, .. } => {
+            let conn = connect(database, auth_token).await?;
+            let reverted = rollback_with_options(&conn, to, &options).await?;
+            for id in &reverted {
+                println!("Reverted {}", id);
+            }
+            println!("{} reverted", reverted.len());
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -9,7 +9,7 @@
 //! ```no_run
 //! # #[cfg(feature = "content")]
 //! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
-//! use libsql_migration::{content::migrate, errors::LibsqlContentMigratorError, util::MigrationResult};
+//! use libsql_migration::{content::migrate, errors::LibsqlContentMigratorError};
 //! use libsql::Builder;
 //!
 //! // Ensure the `content` feature is enabled and default features disabled in Cargo.toml
@@ -23,8 +23,8 @@
 //! let migration_sql = "CREATE TABLE IF NOT EXISTS users_from_content (id INTEGER PRIMARY KEY);".to_string();
 //!
 //! match migrate(&conn, migration_id.clone(), migration_sql).await {
-//!     Ok(MigrationResult::Executed) => println!("Content migration '{}' applied successfully.", migration_id),
-//!     Ok(MigrationResult::AlreadyExecuted) => println!("Content migration '{}' was already applied.", migration_id),
+//!     Ok(report) if report.applied.is_empty() => println!("Content migration '{}' was already applied.", migration_id),
+//!     Ok(_) => println!("Content migration '{}' applied successfully.", migration_id),
 //!     Err(e) => eprintln!("Content migration '{}' failed: {}", migration_id, e),
 //! }
 //! # Ok(())
@@ -32,28 +32,253 @@
 //! ```
 
 use crate::errors::LibsqlContentMigratorError;
-use crate::util::{MigrationResult, create_migration_table, execute_migration};
+use crate::migrator::{MigrationSource, Migrator};
+use crate::util::{
+    FailedMigration, MigrateOptions, Migration, MigrationReport, MigrationStatus,
+    query_failed_migrations, rollback_migrations,
+};
 use libsql::Connection;
 
+/// Applies `migration_script` under `migration_id` unless it was already
+/// applied. The returned [`MigrationReport`] lists the id as applied or skipped.
 pub async fn migrate(
     conn: &Connection,
     migration_id: String,
     migration_script: String,
-) -> Result<MigrationResult, LibsqlContentMigratorError> {
-    if migration_id.is_empty() {
+) -> Result<MigrationReport, LibsqlContentMigratorError> {
+    migrate_batch(
+        conn,
+        vec![ContentMigration::new(migration_id, migration_script)],
+    )
+    .await
+}
+
+/// Same as [`migrate`], additionally storing `down_script` so the migration can
+/// later be reverted with [`rollback`].
+pub async fn migrate_with_down(
+    conn: &Connection,
+    migration_id: String,
+    migration_script: String,
+    down_script: String,
+) -> Result<MigrationReport, LibsqlContentMigratorError> {
+    migrate_batch(
+        conn,
+        vec![ContentMigration::new(migration_id, migration_script).with_down(down_script)],
+    )
+    .await
+}
+
+/// Same as [`migrate`], configured by `options`.
+///
+/// With [`MigrateOptions::dry_run`] set, nothing is executed and the report tells
+/// whether the migration would be applied.
+pub async fn migrate_with_options(
+    conn: &Connection,
+    migration_id: String,
+    migration_script: String,
+    options: &MigrateOptions,
+) -> Result<MigrationReport, LibsqlContentMigratorError> {
+    migrate_batch_with_options(
+        conn,
+        vec![ContentMigration::new(migration_id, migration_script)],
+        options,
+    )
+    .await
+}
+
+/// A migration applied by [`migrate_batch`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentMigration {
+    pub id: String,
+    pub script: String,
+    /// Script that reverts `script`, stored so the migration can later be reverted
+    /// with [`rollback`].
+    pub down_script: Option<String>,
+}
+
+impl ContentMigration {
+    pub fn new(id: impl Into<String>, script: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            script: script.into(),
+            down_script: None,
+        }
+    }
+
+    pub fn with_down(mut self, down_script: impl Into<String>) -> Self {
+        self.down_script = Some(down_script.into());
+        self
+    }
+}
+
+/// A list of [`ContentMigration`]s as a [`MigrationSource`].
+///
+/// The migrations are applied in the order of the list. Every migration is
+/// validated before anything is executed: ids and scripts must not be empty and
+/// ids must be unique.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentSource(pub Vec<ContentMigration>);
+
+impl MigrationSource for ContentSource {
+    type Error = LibsqlContentMigratorError;
+
+    async fn migrations(&self) -> Result<Vec<Migration>, Self::Error> {
+        let mut validated: Vec<Migration> = Vec::with_capacity(self.0.len());
+
+        for migration in &self.0 {
+            validate_migration(migration)?;
+
+            if validated.iter().any(|other| other.id == migration.id) {
+                return Err(LibsqlContentMigratorError::InvalidInput(format!(
+                    "`migration_id` {} is duplicated",
+                    migration.id
+                )));
+            }
+
+            validated.push(Migration::new(
+                migration.id.clone(),
+                migration.script.clone(),
+                migration.down_script.clone(),
+            ));
+        }
+
+        Ok(validated)
+    }
+}
+
+/// Applies the pending migrations of `migrations` in the given order, in a single
+/// run, and returns the combined [`MigrationReport`].
+///
+/// Every migration is validated before anything is executed: ids and scripts must
+/// not be empty and ids must be unique.
+pub async fn migrate_batch(
+    conn: &Connection,
+    migrations: Vec<ContentMigration>,
+) -> Result<MigrationReport, LibsqlContentMigratorError> {
+    migrate_batch_with_options(conn, migrations, &MigrateOptions::default()).await
+}
+
+/// Same as [`migrate_batch`], configured by `options`.
+pub async fn migrate_batch_with_options(
+    conn: &Connection,
+    migrations: Vec<ContentMigration>,
+    options: &MigrateOptions,
+) -> Result<MigrationReport, LibsqlContentMigratorError> {
+    Migrator::new(ContentSource(migrations))
+        .with_options(options.clone())
+        .migrate(conn)
+        .await
+}
+
+fn validate_migration(migration: &ContentMigration) -> Result<(), LibsqlContentMigratorError> {
+    if migration.id.is_empty() {
         return Err(LibsqlContentMigratorError::InvalidInput(
             "`migration_id` is empty".to_string(),
         ));
     }
-    if migration_script.is_empty() {
+    if migration.script.is_empty() {
         return Err(LibsqlContentMigratorError::InvalidInput(
             "`migration_script` is empty".to_string(),
         ));
     }
+    if migration.down_script.as_ref().is_some_and(String::is_empty) {
+        return Err(LibsqlContentMigratorError::InvalidInput(
+            "`down_script` is empty".to_string(),
+        ));
+    }
+
+    Ok(())
+}
 
-    create_migration_table(conn).await?;
+/// Checks that `migration_script` matches the script applied under `migration_id`.
+///
+/// Returns [`LibsqlMigratorBaseError::ChecksumMismatch`](crate::errors::LibsqlMigratorBaseError::ChecksumMismatch)
+/// (wrapped in [`LibsqlContentMigratorError::BaseError`]) if the applied script differs.
+/// A migration that has not been applied yet is not checked.
+pub async fn verify(
+    conn: &Connection,
+    migration_id: String,
+    migration_script: String,
+) -> Result<(), LibsqlContentMigratorError> {
+    verify_with_options(
+        conn,
+        migration_id,
+        migration_script,
+        &MigrateOptions::default(),
+    )
+    .await
+}
 
-    let res = execute_migration(conn, migration_id, migration_script).await?;
+/// Same as [`verify`], configured by `options`.
+pub async fn verify_with_options(
+    conn: &Connection,
+    migration_id: String,
+    migration_script: String,
+    options: &MigrateOptions,
+) -> Result<(), LibsqlContentMigratorError> {
+    Migrator::new(ContentSource(vec![ContentMigration::new(
+        migration_id,
+        migration_script,
+    )]))
+    .with_options(options.clone())
+    .verify(conn)
+    .await
+}
 
-    Ok(res)
+/// Reports the state of every migration in `migrations`, plus applied migrations
+/// that are not in the list, without applying anything.
+pub async fn status(
+    conn: &Connection,
+    migrations: Vec<ContentMigration>,
+) -> Result<Vec<MigrationStatus>, LibsqlContentMigratorError> {
+    status_with_options(conn, migrations, &MigrateOptions::default()).await
+}
+
+/// Same as [`status`], configured by `options`.
+pub async fn status_with_options(
+    conn: &Connection,
+    migrations: Vec<ContentMigration>,
+    options: &MigrateOptions,
+) -> Result<Vec<MigrationStatus>, LibsqlContentMigratorError> {
+    Migrator::new(ContentSource(migrations))
+        .with_options(options.clone())
+        .status(conn)
+        .await
+}
+
+/// Reverts every migration applied after `target_id`, newest first, or every
+/// applied migration when `target_id` is `None`.
+///
+/// Uses the down scripts stored by [`migrate_with_down`] and removes the reverted
+/// migrations from `libsql_migrations`. Returns the reverted ids.
+pub async fn rollback(
+    conn: &Connection,
+    target_id: Option<String>,
+) -> Result<Vec<String>, LibsqlContentMigratorError> {
+    rollback_with_options(conn, target_id, &MigrateOptions::default()).await
+}
+
+/// Same as [`rollback`], configured by `options`.
+pub async fn rollback_with_options(
+    conn: &Connection,
+    target_id: Option<String>,
+    options: &MigrateOptions,
+) -> Result<Vec<String>, LibsqlContentMigratorError> {
+    Ok(rollback_migrations(conn, options, target_id).await?)
+}
+
+/// Lists the migrations whose last attempt failed, with the recorded error, the
+/// failing statement, the number of attempts and the time of the last attempt.
+pub async fn failed_migrations(
+    conn: &Connection,
+) -> Result<Vec<FailedMigration>, LibsqlContentMigratorError> {
+    failed_migrations_with_options(conn, &MigrateOptions::default()).await
+}
+
+/// Same as [`failed_migrations`], configured by `options`.
+pub async fn failed_migrations_with_options(
+    conn: &Connection,
+    options: &MigrateOptions,
+) -> Result<Vec<FailedMigration>, LibsqlContentMigratorError> {
+    Ok(query_failed_migrations(conn, &options.table).await?)
 }
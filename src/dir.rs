@@ -1,8 +1,21 @@
 //! Provides migration capabilities using SQL files from a local directory.
 //!
 //! This module is activated by the `dir` feature (enabled by default).
-//! It finds `.sql` files in a specified directory, sorts them lexicographically,
-//! and applies them sequentially if they haven't been applied before.
+//! It finds `.sql` files in a specified directory, sorts them lexicographically
+//! (or by the [`OrderingStrategy`](crate::util::OrderingStrategy) of
+//! [`MigrateOptions::strategy`]), and applies them sequentially if they haven't
+//! been applied before.
+//!
+//! Files in subdirectories are migrations too, identified by their path relative
+//! to the directory, or by their file name alone with [`NestedDirMode::Flat`].
+//!
+//! A migration can be paired with a down script by naming the files
+//! `0003_x.up.sql` and `0003_x.down.sql`. The `.up.sql` file is applied like any
+//! other migration (its relative path is the id) and the `.down.sql` file is stored
+//! with it, so it can later be reverted with [`rollback`].
+//!
+//! New migration files can be created with [`create_migration`], which names them
+//! `<prefix>_<description>.sql` with a timestamp or next-sequence prefix.
 //!
 //! # Usage
 //!
@@ -27,12 +40,15 @@
 //! // - 0002_add_email_to_users.sql
 //!
 //! match migrate(&conn, migrations_folder).await {
-//!     Ok(applied) => {
-//!         if applied {
-//!             println!("Directory migrations applied successfully.");
-//!         } else {
-//!             println!("No new directory migrations to apply.");
+//!     Ok(report) => {
+//!         for id in &report.applied {
+//!             println!("Applied {} in {:?}", id, report.durations[id]);
 //!         }
+//!         println!(
+//!             "Directory migrations done in {:?} ({} already applied)",
+//!             report.total_duration,
+//!             report.skipped.len()
+//!         );
 //!     }
 //!     Err(e) => eprintln!("Directory migration failed: {}", e),
 //! }
@@ -41,11 +57,35 @@
 //! ```
 
 use crate::errors::LibsqlDirMigratorError;
+use crate::files::migrations_from_files;
+use crate::files::{DOWN_SUFFIX, UP_SUFFIX};
+use crate::migrator::{MigrationSource, Migrator};
 use crate::util::{
-    MigrationResult, create_migration_table, execute_migration, validate_migration_folder,
+    FailedMigration, MigrateOptions, Migration, MigrationReport, MigrationStatus, NestedDirMode,
+    PlannedMigration, applied_ids, compare_ids, query_failed_migrations, rollback_migrations,
 };
 use libsql::Connection;
-use std::{fs, io, path::PathBuf};
+use std::{
+    cmp::Ordering,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+fn validate_migration_folder(path: &Path) -> Result<(), LibsqlDirMigratorError> {
+    if !path.exists() {
+        return Err(LibsqlDirMigratorError::MigrationDirNotFound(
+            path.to_path_buf(),
+        ));
+    };
+    if path.is_dir() || (path.is_file() && path.extension().unwrap_or_default() == "sql") {
+        Ok(())
+    } else {
+        Err(LibsqlDirMigratorError::InvalidMigrationPath(
+            path.to_path_buf(),
+        ))
+    }
+}
 
 fn check_dir_for_sql_files(root_path: PathBuf) -> Result<Vec<PathBuf>, io::Error> {
     let mut file_paths: Vec<PathBuf> = vec![];
@@ -63,48 +103,403 @@ fn check_dir_for_sql_files(root_path: PathBuf) -> Result<Vec<PathBuf>, io::Error
         }
     }
 
-    file_paths.sort_by(|a, b| {
-        let a_name = a.file_name().unwrap_or_default();
-        let b_name = b.file_name().unwrap_or_default();
-        a_name.cmp(b_name)
-    });
-
     Ok(file_paths)
 }
 
+fn read_sql_file(
+    migrations_folder: &PathBuf,
+    file: &PathBuf,
+) -> Result<String, LibsqlDirMigratorError> {
+    fs::read_to_string(file).map_err(|_| {
+        LibsqlDirMigratorError::ErrorWhileGettingSQLFiles(format!(
+            "Unable to read {:?} file!",
+            file.strip_prefix(migrations_folder)
+                .unwrap_or(file)
+                .to_str()
+        ))
+    })
+}
+
+/// Fails with [`LibsqlDirMigratorError::ConflictingMigrationFiles`] listing every
+/// file whose name is used by another file. `paths` are relative to the folder.
+fn check_conflicting_files(paths: &[String]) -> Result<(), LibsqlDirMigratorError> {
+    let file_name = |path: &String| Path::new(path).file_name().map(|name| name.to_owned());
+
+    let mut conflicting: Vec<String> = paths
+        .iter()
+        .filter(|path| {
+            paths
+                .iter()
+                .filter(|other| file_name(other) == file_name(path))
+                .count()
+                > 1
+        })
+        .cloned()
+        .collect();
+
+    if conflicting.is_empty() {
+        return Ok(());
+    }
+
+    conflicting.sort_by(|a, b| compare_ids(a, b));
+    Err(LibsqlDirMigratorError::ConflictingMigrationFiles(
+        conflicting,
+    ))
+}
+
+fn read_migration_files(
+    migrations_folder: &PathBuf,
+    nested_dirs: NestedDirMode,
+) -> Result<Vec<Migration>, LibsqlDirMigratorError> {
+    let files = check_dir_for_sql_files(migrations_folder.clone())
+        .map_err(|e| LibsqlDirMigratorError::ErrorWhileGettingSQLFiles(e.to_string()))?;
+
+    let paths: Vec<String> = files
+        .iter()
+        .map(|file| {
+            let file_id = file.strip_prefix(migrations_folder).unwrap();
+            file_id.to_str().unwrap().to_string()
+        })
+        .collect();
+
+    if nested_dirs == NestedDirMode::Flat {
+        check_conflicting_files(&paths)?;
+    }
+
+    let mut contents = vec![];
+
+    for (file, path) in files.iter().zip(paths) {
+        let file_id = match nested_dirs {
+            NestedDirMode::Namespaced => path,
+            NestedDirMode::Flat => file.file_name().unwrap().to_str().unwrap().to_string(),
+        };
+        contents.push((file_id, read_sql_file(migrations_folder, file)?));
+    }
+
+    migrations_from_files(contents).map_err(LibsqlDirMigratorError::ErrorWhileGettingSQLFiles)
+}
+
+/// The `.sql` files of a local directory as a [`MigrationSource`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirSource {
+    migrations_folder: PathBuf,
+    nested_dirs: NestedDirMode,
+}
+
+impl DirSource {
+    pub fn new(migrations_folder: impl Into<PathBuf>) -> Self {
+        Self {
+            migrations_folder: migrations_folder.into(),
+            nested_dirs: NestedDirMode::default(),
+        }
+    }
+
+    /// Identifies files in subdirectories as set by `nested_dirs`.
+    pub fn with_nested_dirs(mut self, nested_dirs: NestedDirMode) -> Self {
+        self.nested_dirs = nested_dirs;
+        self
+    }
+}
+
+impl MigrationSource for DirSource {
+    type Error = LibsqlDirMigratorError;
+
+    async fn migrations(&self) -> Result<Vec<Migration>, Self::Error> {
+        validate_migration_folder(&self.migrations_folder)?;
+
+        read_migration_files(&self.migrations_folder, self.nested_dirs)
+    }
+}
+
+/// The source the `*_with_options` functions read `migrations_folder` with.
+fn source(migrations_folder: PathBuf, options: &MigrateOptions) -> DirSource {
+    DirSource::new(migrations_folder).with_nested_dirs(options.nested_dirs)
+}
+
 pub async fn migrate(
     conn: &Connection,
     migrations_folder: PathBuf,
-) -> Result<bool, LibsqlDirMigratorError> {
-    validate_migration_folder(&migrations_folder)?;
+) -> Result<MigrationReport, LibsqlDirMigratorError> {
+    migrate_with_options(conn, migrations_folder, &MigrateOptions::default()).await
+}
+
+/// Same as [`migrate`], configured by `options`.
+///
+/// With [`MigrateOptions::dry_run`] set, the files are discovered and read but
+/// nothing is executed, and the report lists the migrations that would be applied.
+pub async fn migrate_with_options(
+    conn: &Connection,
+    migrations_folder: PathBuf,
+    options: &MigrateOptions,
+) -> Result<MigrationReport, LibsqlDirMigratorError> {
+    Migrator::new(source(migrations_folder, options))
+        .with_options(options.clone())
+        .migrate(conn)
+        .await
+}
+
+/// Lists the migrations in `migrations_folder` that [`migrate`] would apply, in
+/// the order it would apply them, without executing anything.
+pub async fn plan(
+    conn: &Connection,
+    migrations_folder: PathBuf,
+) -> Result<Vec<PlannedMigration>, LibsqlDirMigratorError> {
+    plan_with_options(conn, migrations_folder, &MigrateOptions::default()).await
+}
+
+/// Same as [`plan`], configured by `options`.
+pub async fn plan_with_options(
+    conn: &Connection,
+    migrations_folder: PathBuf,
+    options: &MigrateOptions,
+) -> Result<Vec<PlannedMigration>, LibsqlDirMigratorError> {
+    Migrator::new(source(migrations_folder, options))
+        .with_options(options.clone())
+        .plan(conn)
+        .await
+}
+
+/// Checks that no already applied file in `migrations_folder` has been edited since
+/// it was applied.
+///
+/// Returns [`LibsqlMigratorBaseError::ChecksumMismatch`](crate::errors::LibsqlMigratorBaseError::ChecksumMismatch)
+/// (wrapped in [`LibsqlDirMigratorError::BaseError`]) listing the modified files.
+pub async fn verify(
+    conn: &Connection,
+    migrations_folder: PathBuf,
+) -> Result<(), LibsqlDirMigratorError> {
+    verify_with_options(conn, migrations_folder, &MigrateOptions::default()).await
+}
+
+/// Same as [`verify`], configured by `options`.
+pub async fn verify_with_options(
+    conn: &Connection,
+    migrations_folder: PathBuf,
+    options: &MigrateOptions,
+) -> Result<(), LibsqlDirMigratorError> {
+    Migrator::new(source(migrations_folder, options))
+        .with_options(options.clone())
+        .verify(conn)
+        .await
+}
 
-    create_migration_table(conn).await?;
+/// Reports the state of every migration in `migrations_folder`, plus applied
+/// migrations whose files no longer exist, without applying anything.
+pub async fn status(
+    conn: &Connection,
+    migrations_folder: PathBuf,
+) -> Result<Vec<MigrationStatus>, LibsqlDirMigratorError> {
+    status_with_options(conn, migrations_folder, &MigrateOptions::default()).await
+}
+
+/// Same as [`status`], configured by `options`.
+pub async fn status_with_options(
+    conn: &Connection,
+    migrations_folder: PathBuf,
+    options: &MigrateOptions,
+) -> Result<Vec<MigrationStatus>, LibsqlDirMigratorError> {
+    Migrator::new(source(migrations_folder, options))
+        .with_options(options.clone())
+        .status(conn)
+        .await
+}
+
+/// Reverts every migration applied after `target_id`, newest first, or every
+/// applied migration when `target_id` is `None`.
+///
+/// Uses the `.down.sql` scripts recorded when each `.up.sql` file was applied and
+/// removes the reverted migrations from `libsql_migrations`. Returns the reverted ids.
+pub async fn rollback(
+    conn: &Connection,
+    target_id: Option<String>,
+) -> Result<Vec<String>, LibsqlDirMigratorError> {
+    rollback_with_options(conn, target_id, &MigrateOptions::default()).await
+}
+
+/// Same as [`rollback`], configured by `options`.
+pub async fn rollback_with_options(
+    conn: &Connection,
+    target_id: Option<String>,
+    options: &MigrateOptions,
+) -> Result<Vec<String>, LibsqlDirMigratorError> {
+    Ok(rollback_migrations(conn, options, target_id).await?)
+}
+
+/// Lists the migrations whose last attempt failed, with the recorded error, the
+/// failing statement, the number of attempts and the time of the last attempt.
+pub async fn failed_migrations(
+    conn: &Connection,
+) -> Result<Vec<FailedMigration>, LibsqlDirMigratorError> {
+    failed_migrations_with_options(conn, &MigrateOptions::default()).await
+}
 
-    let files_to_run = check_dir_for_sql_files(migrations_folder.clone())
+/// Same as [`failed_migrations`], configured by `options`.
+pub async fn failed_migrations_with_options(
+    conn: &Connection,
+    options: &MigrateOptions,
+) -> Result<Vec<FailedMigration>, LibsqlDirMigratorError> {
+    Ok(query_failed_migrations(conn, &options.table).await?)
+}
+
+/// How [`create_migration`] prefixes the name of a new migration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MigrationPrefix {
+    /// The current UTC time as `YYYYMMDDHHMMSS`, which does not collide across
+    /// branches.
+    #[default]
+    Timestamp,
+    /// The highest numeric prefix in the folder plus one, as `0001`, `0002`, ...
+    Sequence,
+}
+
+/// Options accepted by [`create_migration_with_options`].
+#[derive(Debug, Clone, Default)]
+pub struct CreateMigrationOptions {
+    pub prefix: MigrationPrefix,
+    /// Create a `.up.sql`/`.down.sql` pair instead of a single `.sql` file.
+    pub reversible: bool,
+    /// Options the migrations are applied with. The applied ids are read from
+    /// its `table` and compared with the new one using its `strategy`.
+    pub migrate: MigrateOptions,
+}
+
+fn slugify(description: &str) -> String {
+    description
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Formats `time` as `YYYYMMDDHHMMSS` in UTC.
+fn timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+
+    // Days since 1970-01-01 to a civil date, from Howard Hinnant's algorithm.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{:04}{:02}{:02}{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60
+    )
+}
+
+fn next_sequence(migrations_folder: &Path) -> Result<String, LibsqlDirMigratorError> {
+    let files = check_dir_for_sql_files(migrations_folder.to_path_buf())
         .map_err(|e| LibsqlDirMigratorError::ErrorWhileGettingSQLFiles(e.to_string()))?;
 
-    if files_to_run.is_empty() {
-        return Ok(false);
-    };
+    let last = files
+        .iter()
+        .filter_map(|file| {
+            let name = file.file_name()?.to_str()?;
+            let digits: String = name.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse::<u64>().ok()
+        })
+        .max()
+        .unwrap_or(0);
 
-    let mut did_new_migration = false;
+    Ok(format!("{:04}", last + 1))
+}
 
-    for file in files_to_run {
-        let file_id = file.strip_prefix(&migrations_folder).unwrap();
+/// Creates an empty migration file named `<prefix>_<description>.sql` in
+/// `migrations_folder`, with a timestamp prefix. Returns the created paths.
+///
+/// The description is lowercased and every run of other characters than ASCII
+/// letters and digits becomes `_`. Fails with
+/// [`LibsqlDirMigratorError::MigrationSortsBeforeApplied`] when an applied
+/// migration would sort after the new one, and never overwrites a file.
+pub async fn create_migration(
+    conn: &Connection,
+    migrations_folder: PathBuf,
+    description: &str,
+) -> Result<Vec<PathBuf>, LibsqlDirMigratorError> {
+    create_migration_with_options(
+        Some(conn),
+        migrations_folder,
+        description,
+        &CreateMigrationOptions::default(),
+    )
+    .await
+}
 
-        let file_data = fs::read_to_string(&file).map_err(|_| {
-            LibsqlDirMigratorError::ErrorWhileGettingSQLFiles(format!(
-                "Unable to read {:?} file!",
-                file_id.to_str()
-            ))
-        })?;
+/// Same as [`create_migration`], configured by `options`. Returns the up file
+/// first when a pair is created.
+///
+/// Without `conn`, the new id is not checked against the applied migrations.
+pub async fn create_migration_with_options(
+    conn: Option<&Connection>,
+    migrations_folder: PathBuf,
+    description: &str,
+    options: &CreateMigrationOptions,
+) -> Result<Vec<PathBuf>, LibsqlDirMigratorError> {
+    let slug = slugify(description);
+    if slug.is_empty() {
+        return Err(LibsqlDirMigratorError::InvalidMigrationName(
+            description.to_string(),
+        ));
+    }
 
-        if let MigrationResult::Executed =
-            execute_migration(conn, file_id.to_str().unwrap().to_string(), file_data).await?
+    fs::create_dir_all(&migrations_folder)
+        .map_err(|e| LibsqlDirMigratorError::ErrorWhileCreatingSQLFiles(e.to_string()))?;
+
+    let prefix = match options.prefix {
+        MigrationPrefix::Timestamp => timestamp(SystemTime::now()),
+        MigrationPrefix::Sequence => next_sequence(&migrations_folder)?,
+    };
+    let stem = format!("{}_{}", prefix, slug);
+    let suffixes = if options.reversible {
+        vec![UP_SUFFIX, DOWN_SUFFIX]
+    } else {
+        vec![".sql"]
+    };
+
+    let id = format!("{}{}", stem, suffixes[0]);
+    if let Some(conn) = conn {
+        let strategy = options.migrate.strategy;
+        if let Some(applied_id) = applied_ids(conn, &options.migrate.table)
+            .await?
+            .into_iter()
+            .filter(|applied_id| strategy.compare(&id, applied_id) == Ordering::Less)
+            .max_by(|a, b| strategy.compare(a, b))
         {
-            did_new_migration = true
+            return Err(LibsqlDirMigratorError::MigrationSortsBeforeApplied { id, applied_id });
         }
     }
 
-    Ok(did_new_migration)
+    let mut created = vec![];
+    for suffix in suffixes {
+        let path = migrations_folder.join(format!("{}{}", stem, suffix));
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|e| {
+                LibsqlDirMigratorError::ErrorWhileCreatingSQLFiles(format!(
+                    "Unable to create {:?}: {}",
+                    path, e
+                ))
+            })?;
+        created.push(path);
+    }
+
+    Ok(created)
 }
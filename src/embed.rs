@@ -0,0 +1,199 @@
+//! Provides migration capabilities using SQL files embedded into the binary at
+//! compile time.
+//!
+//! This module is activated by the `embed` feature. The [`embed_migrations!`](crate::embed_migrations)
+//! macro reads a migrations directory while the crate is built, so the directory
+//! does not need to be shipped with the executable. The files are ordered, paired
+//! (`.up.sql`/`.down.sql`) and tracked exactly like the [`dir`](crate::dir) module
+//! does, with the path relative to the directory as id.
+//!
+//! Adding or removing a file does not trigger a rebuild on its own. Add a build
+//! script containing `println!("cargo:rerun-if-changed=migrations");` to pick up
+//! such changes.
+//!
+//! # Usage
+//!
+//! ```ignore
+//! # #[cfg(feature = "embed")]
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! use libsql_migration::{embed::{EmbeddedMigrations, migrate}, embed_migrations};
+//! use libsql::Builder;
+//!
+//! // Ensure the `embed` feature is enabled in Cargo.toml
+//! // [dependencies]
+//! // libsql_migration = { version = "...", features = ["embed"] }
+//!
+//! // Path relative to the Cargo.toml of your crate.
+//! static MIGRATIONS: EmbeddedMigrations = embed_migrations!("./migrations");
+//!
+//! let db = Builder::new_local("my_database.db").build().await.unwrap();
+//! let conn = db.connect().unwrap();
+//!
+//! match migrate(&conn, &MIGRATIONS).await {
+//!     Ok(report) => println!("Applied {:?}", report.applied),
+//!     Err(e) => eprintln!("Embedded migration failed: {}", e),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::errors::LibsqlEmbedMigratorError;
+use crate::files::migrations_from_files;
+use crate::migrator::{MigrationSource, Migrator};
+use crate::util::{
+    FailedMigration, MigrateOptions, Migration, MigrationReport, MigrationStatus, PlannedMigration,
+    query_failed_migrations, rollback_migrations,
+};
+use libsql::Connection;
+
+/// The `.sql` files of a migrations directory, as produced by
+/// [`embed_migrations!`](crate::embed_migrations).
+#[derive(Debug, Clone, Copy)]
+pub struct EmbeddedMigrations {
+    files: &'static [(&'static str, &'static str)],
+}
+
+impl EmbeddedMigrations {
+    /// Builds the set from `(relative path, content)` pairs. Use
+    /// [`embed_migrations!`](crate::embed_migrations) instead of calling this directly.
+    pub const fn new(files: &'static [(&'static str, &'static str)]) -> Self {
+        Self { files }
+    }
+
+    /// Relative paths of the embedded files, in no particular order.
+    pub fn files(&self) -> impl Iterator<Item = &'static str> {
+        self.files.iter().map(|(path, _)| *path)
+    }
+}
+
+impl MigrationSource for EmbeddedMigrations {
+    type Error = LibsqlEmbedMigratorError;
+
+    async fn migrations(&self) -> Result<Vec<Migration>, Self::Error> {
+        migrations_from_files(
+            self.files
+                .iter()
+                .map(|(path, content)| (path.to_string(), content.to_string()))
+                .collect(),
+        )
+        .map_err(LibsqlEmbedMigratorError::InvalidMigrationFiles)
+    }
+}
+
+pub async fn migrate(
+    conn: &Connection,
+    migrations: &EmbeddedMigrations,
+) -> Result<MigrationReport, LibsqlEmbedMigratorError> {
+    migrate_with_options(conn, migrations, &MigrateOptions::default()).await
+}
+
+/// Same as [`migrate`], configured by `options`.
+pub async fn migrate_with_options(
+    conn: &Connection,
+    migrations: &EmbeddedMigrations,
+    options: &MigrateOptions,
+) -> Result<MigrationReport, LibsqlEmbedMigratorError> {
+    Migrator::new(*migrations)
+        .with_options(options.clone())
+        .migrate(conn)
+        .await
+}
+
+/// Lists the embedded migrations that [`migrate`] would apply, in the order it
+/// would apply them, without executing anything.
+pub async fn plan(
+    conn: &Connection,
+    migrations: &EmbeddedMigrations,
+) -> Result<Vec<PlannedMigration>, LibsqlEmbedMigratorError> {
+    plan_with_options(conn, migrations, &MigrateOptions::default()).await
+}
+
+/// Same as [`plan`], configured by `options`.
+pub async fn plan_with_options(
+    conn: &Connection,
+    migrations: &EmbeddedMigrations,
+    options: &MigrateOptions,
+) -> Result<Vec<PlannedMigration>, LibsqlEmbedMigratorError> {
+    Migrator::new(*migrations)
+        .with_options(options.clone())
+        .plan(conn)
+        .await
+}
+
+/// Checks that no already applied migration has been edited since it was applied.
+pub async fn verify(
+    conn: &Connection,
+    migrations: &EmbeddedMigrations,
+) -> Result<(), LibsqlEmbedMigratorError> {
+    verify_with_options(conn, migrations, &MigrateOptions::default()).await
+}
+
+/// Same as [`verify`], configured by `options`.
+pub async fn verify_with_options(
+    conn: &Connection,
+    migrations: &EmbeddedMigrations,
+    options: &MigrateOptions,
+) -> Result<(), LibsqlEmbedMigratorError> {
+    Migrator::new(*migrations)
+        .with_options(options.clone())
+        .verify(conn)
+        .await
+}
+
+/// Reports the state of every embedded migration, plus applied migrations that
+/// are no longer embedded, without applying anything.
+pub async fn status(
+    conn: &Connection,
+    migrations: &EmbeddedMigrations,
+) -> Result<Vec<MigrationStatus>, LibsqlEmbedMigratorError> {
+    status_with_options(conn, migrations, &MigrateOptions::default()).await
+}
+
+/// Same as [`status`], configured by `options`.
+pub async fn status_with_options(
+    conn: &Connection,
+    migrations: &EmbeddedMigrations,
+    options: &MigrateOptions,
+) -> Result<Vec<MigrationStatus>, LibsqlEmbedMigratorError> {
+    Migrator::new(*migrations)
+        .with_options(options.clone())
+        .status(conn)
+        .await
+}
+
+/// Reverts every migration applied after `target_id`, newest first, or every
+/// applied migration when `target_id` is `None`.
+///
+/// Uses the `.down.sql` scripts stored when the migrations were applied. Returns
+/// the reverted ids.
+pub async fn rollback(
+    conn: &Connection,
+    target_id: Option<String>,
+) -> Result<Vec<String>, LibsqlEmbedMigratorError> {
+    rollback_with_options(conn, target_id, &MigrateOptions::default()).await
+}
+
+/// Same as [`rollback`], configured by `options`.
+pub async fn rollback_with_options(
+    conn: &Connection,
+    target_id: Option<String>,
+    options: &MigrateOptions,
+) -> Result<Vec<String>, LibsqlEmbedMigratorError> {
+    Ok(rollback_migrations(conn, options, target_id).await?)
+}
+
+/// Lists the migrations whose last attempt failed, with the recorded error, the
+/// failing statement, the number of attempts and the time of the last attempt.
+pub async fn failed_migrations(
+    conn: &Connection,
+) -> Result<Vec<FailedMigration>, LibsqlEmbedMigratorError> {
+    failed_migrations_with_options(conn, &MigrateOptions::default()).await
+}
+
+/// Same as [`failed_migrations`], configured by `options`.
+pub async fn failed_migrations_with_options(
+    conn: &Connection,
+    options: &MigrateOptions,
+) -> Result<Vec<FailedMigration>, LibsqlEmbedMigratorError> {
+    Ok(query_failed_migrations(conn, &options.table).await?)
+}
@@ -1,7 +1,8 @@
+#[cfg(feature = "dir")]
+use std::path::PathBuf;
 use std::{
     error::Error,
     fmt::{Display, Formatter, Result},
-    path::PathBuf,
 };
 
 use libsql::Error as LibsqlError;
@@ -10,6 +11,36 @@ use libsql::Error as LibsqlError;
 pub enum LibsqlMigratorBaseError {
     LibSqlError(LibsqlError),
     MigrationFailed(String),
+    StatementFailed {
+        migration_id: String,
+        statement_index: usize,
+        statement: String,
+        source: LibsqlError,
+    },
+    ChecksumMismatch(Vec<String>),
+    RollbackTargetNotFound(String),
+    MissingDownMigration(Vec<String>),
+    UnsupportedTableVersion(i64),
+    LockTimeout(String),
+    /// The migration lock expired during the run and was taken over by the
+    /// owner it carries.
+    LockLost(String),
+    DuplicateMigrationId(String),
+    CodeMigrationFailed {
+        migration_id: String,
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// Pending migrations sorting before an already applied one, rejected by
+    /// [`OrderingPolicy::Strict`](crate::util::OrderingPolicy::Strict).
+    OutOfOrderMigrations(Vec<String>),
+    /// An id without the prefix required by the selected
+    /// [`OrderingStrategy`](crate::util::OrderingStrategy).
+    InvalidMigrationVersion(String),
+    /// Two ids resolving to the same version.
+    DuplicateMigrationVersion(String, String),
+    /// A migration listed without its script because it was applied, which was
+    /// reverted before the run could skip it.
+    MigrationNotLoaded(String),
 }
 
 impl Display for LibsqlMigratorBaseError {
@@ -19,6 +50,80 @@ impl Display for LibsqlMigratorBaseError {
             LibsqlMigratorBaseError::MigrationFailed(msg) => {
                 write!(f, "LibsqlMigrationError: Migration failed | {}", msg)
             }
+            LibsqlMigratorBaseError::StatementFailed {
+                migration_id,
+                statement_index,
+                statement,
+                source,
+            } => write!(
+                f,
+                "LibsqlMigrationError: Statement {} of migration {} failed | {} | {}",
+                statement_index, migration_id, source, statement
+            ),
+            LibsqlMigratorBaseError::ChecksumMismatch(ids) => write!(
+                f,
+                "LibsqlMigrationError: Applied migrations were modified | {}",
+                ids.join(", ")
+            ),
+            LibsqlMigratorBaseError::RollbackTargetNotFound(id) => write!(
+                f,
+                "LibsqlMigrationError: Rollback target {} is not an applied migration",
+                id
+            ),
+            LibsqlMigratorBaseError::MissingDownMigration(ids) => write!(
+                f,
+                "LibsqlMigrationError: No down migration recorded for | {}",
+                ids.join(", ")
+            ),
+            LibsqlMigratorBaseError::UnsupportedTableVersion(version) => write!(
+                f,
+                "LibsqlMigrationError: Tracking table version {} is newer than the supported version {}",
+                version,
+                crate::util::MIGRATION_TABLE_VERSION
+            ),
+            LibsqlMigratorBaseError::CodeMigrationFailed {
+                migration_id,
+                source,
+            } => write!(
+                f,
+                "LibsqlMigrationError: Migration {} failed | {}",
+                migration_id, source
+            ),
+            LibsqlMigratorBaseError::DuplicateMigrationId(id) => write!(
+                f,
+                "LibsqlMigrationError: Migration id listed more than once | {}",
+                id
+            ),
+            LibsqlMigratorBaseError::LockTimeout(owner) => write!(
+                f,
+                "LibsqlMigrationError: Timed out waiting for the migration lock held by | {}",
+                owner
+            ),
+            LibsqlMigratorBaseError::LockLost(owner) => write!(
+                f,
+                "LibsqlMigrationError: The migration lock expired and was taken over by | {}",
+                owner
+            ),
+            LibsqlMigratorBaseError::OutOfOrderMigrations(ids) => write!(
+                f,
+                "LibsqlMigrationError: Pending migrations sort before already applied ones | {}",
+                ids.join(", ")
+            ),
+            LibsqlMigratorBaseError::InvalidMigrationVersion(id) => write!(
+                f,
+                "LibsqlMigrationError: Migration id has no version prefix | {}",
+                id
+            ),
+            LibsqlMigratorBaseError::DuplicateMigrationVersion(first, second) => write!(
+                f,
+                "LibsqlMigrationError: Migrations have the same version | {}, {}",
+                first, second
+            ),
+            LibsqlMigratorBaseError::MigrationNotLoaded(id) => write!(
+                f,
+                "LibsqlMigrationError: Migration is no longer applied and its script was not loaded | {}",
+                id
+            ),
         }
     }
 }
@@ -33,6 +138,8 @@ impl Error for LibsqlMigratorBaseError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             LibsqlMigratorBaseError::LibSqlError(e) => Some(e),
+            LibsqlMigratorBaseError::StatementFailed { source, .. } => Some(source),
+            LibsqlMigratorBaseError::CodeMigrationFailed { source, .. } => Some(source.as_ref()),
             _ => None,
         }
     }
@@ -90,6 +197,16 @@ pub enum LibsqlDirMigratorError {
     MigrationDirNotFound(PathBuf),
     InvalidMigrationPath(PathBuf),
     ErrorWhileGettingSQLFiles(String),
+    ErrorWhileCreatingSQLFiles(String),
+    InvalidMigrationName(String),
+    /// The new migration `id` would sort before the already applied `applied_id`.
+    MigrationSortsBeforeApplied {
+        id: String,
+        applied_id: String,
+    },
+    /// Files sharing a file name in [`NestedDirMode::Flat`](crate::util::NestedDirMode::Flat),
+    /// as paths relative to the migrations directory.
+    ConflictingMigrationFiles(Vec<String>),
 }
 
 #[cfg(feature = "dir")]
@@ -116,6 +233,26 @@ impl Display for LibsqlDirMigratorError {
                 "LibsqlDirMigratorError: Error occured while traversing migration folder | {}",
                 msg
             ),
+            LibsqlDirMigratorError::ErrorWhileCreatingSQLFiles(msg) => write!(
+                f,
+                "LibsqlDirMigratorError: Error occured while creating migration files | {}",
+                msg
+            ),
+            LibsqlDirMigratorError::InvalidMigrationName(name) => write!(
+                f,
+                "LibsqlDirMigratorError: {:?} is not a valid migration name",
+                name
+            ),
+            LibsqlDirMigratorError::MigrationSortsBeforeApplied { id, applied_id } => write!(
+                f,
+                "LibsqlDirMigratorError: {} would sort before the applied migration {}",
+                id, applied_id
+            ),
+            LibsqlDirMigratorError::ConflictingMigrationFiles(paths) => write!(
+                f,
+                "LibsqlDirMigratorError: Migration files share the same name | {}",
+                paths.join(", ")
+            ),
         }
     }
 }
@@ -154,6 +291,28 @@ pub enum LibsqlRemoteMigratorError {
     BaseError(LibsqlMigratorBaseError),
     ReqwestError(ReqwestError),
     MigrationUrlNotValid(String),
+    /// A manifest or script request answered with a status other than `2xx`.
+    UnexpectedStatus {
+        url: String,
+        status: u16,
+    },
+    /// A script whose SHA-256 differs from the `sha256` of its manifest entry.
+    IntegrityMismatch {
+        id: String,
+        url: String,
+        expected: String,
+        actual: String,
+    },
+    /// The manifest signature is missing, malformed or does not match the key.
+    InvalidManifestSignature {
+        url: String,
+        reason: String,
+    },
+    /// An entry of a signed manifest without the hashes of its scripts.
+    MissingIntegrityHash(String),
+    /// A manifest that is not a valid list of migrations, or an entry with both or
+    /// neither of `url` and `sql`.
+    InvalidManifest(String),
 }
 
 #[cfg(feature = "remote")]
@@ -165,6 +324,34 @@ impl Display for LibsqlRemoteMigratorError {
             LibsqlRemoteMigratorError::MigrationUrlNotValid(string) => {
                 write!(f, "LibsqlRemoteMigratorError: Invalid URL {}", string)
             }
+            LibsqlRemoteMigratorError::UnexpectedStatus { url, status } => write!(
+                f,
+                "LibsqlRemoteMigratorError: Request failed with status {} | {}",
+                status, url
+            ),
+            LibsqlRemoteMigratorError::IntegrityMismatch {
+                id,
+                url,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "LibsqlRemoteMigratorError: Script of {} does not match its sha256 | {} expected {}, got {}",
+                id, url, expected, actual
+            ),
+            LibsqlRemoteMigratorError::InvalidManifestSignature { url, reason } => write!(
+                f,
+                "LibsqlRemoteMigratorError: Invalid manifest signature | {}: {}",
+                url, reason
+            ),
+            LibsqlRemoteMigratorError::MissingIntegrityHash(id) => write!(
+                f,
+                "LibsqlRemoteMigratorError: Signed manifest entry has no sha256 | {}",
+                id
+            ),
+            LibsqlRemoteMigratorError::InvalidManifest(msg) => {
+                write!(f, "LibsqlRemoteMigratorError: Invalid manifest | {}", msg)
+            }
         }
     }
 }
@@ -199,3 +386,49 @@ impl From<ReqwestError> for LibsqlRemoteMigratorError {
         LibsqlRemoteMigratorError::ReqwestError(value)
     }
 }
+
+// LibsqlEmbedMigratorError
+#[cfg(feature = "embed")]
+#[derive(Debug)]
+pub enum LibsqlEmbedMigratorError {
+    BaseError(LibsqlMigratorBaseError),
+    InvalidMigrationFiles(String),
+}
+
+#[cfg(feature = "embed")]
+impl Display for LibsqlEmbedMigratorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            LibsqlEmbedMigratorError::BaseError(e) => write!(f, "{}", e),
+            LibsqlEmbedMigratorError::InvalidMigrationFiles(msg) => write!(
+                f,
+                "LibsqlEmbedMigratorError: Invalid embedded migration files | {}",
+                msg
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "embed")]
+impl Error for LibsqlEmbedMigratorError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LibsqlEmbedMigratorError::BaseError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "embed")]
+impl From<LibsqlMigratorBaseError> for LibsqlEmbedMigratorError {
+    fn from(value: LibsqlMigratorBaseError) -> Self {
+        LibsqlEmbedMigratorError::BaseError(value)
+    }
+}
+
+#[cfg(feature = "embed")]
+impl From<LibsqlError> for LibsqlEmbedMigratorError {
+    fn from(value: LibsqlError) -> Self {
+        LibsqlEmbedMigratorError::BaseError(LibsqlMigratorBaseError::LibSqlError(value))
+    }
+}
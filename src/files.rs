@@ -0,0 +1,40 @@
+use crate::util::{Migration, compare_ids};
+
+pub(crate) const UP_SUFFIX: &str = ".up.sql";
+pub(crate) const DOWN_SUFFIX: &str = ".down.sql";
+
+/// Turns the `.sql` files of a migrations directory into migrations.
+///
+/// `files` holds the path of each file relative to the directory, used as the
+/// migration id, and its content. Files are ordered by file name, then by path,
+/// and every `.up.sql` file is paired with the `.down.sql` file next to it.
+/// Returns an error message if a `.down.sql` file has no matching `.up.sql` file.
+pub(crate) fn migrations_from_files(
+    mut files: Vec<(String, String)>,
+) -> Result<Vec<Migration>, String> {
+    files.sort_by(|(a, _), (b, _)| compare_ids(a, b));
+
+    let mut migrations = vec![];
+
+    for (path, content) in &files {
+        if let Some(stem) = path.strip_suffix(DOWN_SUFFIX) {
+            let up_path = format!("{}{}", stem, UP_SUFFIX);
+            if !files.iter().any(|(other, _)| *other == up_path) {
+                return Err(format!("{:?} has no matching {} file", path, UP_SUFFIX));
+            }
+            continue;
+        }
+
+        let down_script = path.strip_suffix(UP_SUFFIX).and_then(|stem| {
+            let down_path = format!("{}{}", stem, DOWN_SUFFIX);
+            files
+                .iter()
+                .find(|(other, _)| *other == down_path)
+                .map(|(_, down)| down.clone())
+        });
+
+        migrations.push(Migration::new(path.clone(), content.clone(), down_script));
+    }
+
+    Ok(migrations)
+}
@@ -38,6 +38,13 @@
 //! unique identifiers (e.g., filenames for `dir`, provided IDs for `content`/`remote`)
 //! of the migrations that have been successfully applied. Before applying any
 //! migration, the crate checks this table to prevent reapplying already executed scripts.
+//! The table name and attached-database schema can be changed through
+//! [`util::MigrationTable`] in [`util::MigrateOptions`].
+//!
+//! Each migration script and its tracking row are applied inside a single transaction,
+//! so a failing script leaves the database exactly as it was. Scripts whose first line
+//! is [`util::NO_TRANSACTION_DIRECTIVE`] (`-- no-transaction`) are run outside a
+//! transaction, for statements such as `VACUUM` that SQLite refuses inside one.
 //!
 //! ## Features
 //!
@@ -51,6 +58,8 @@
 //! *   **`remote`**: Migrates using SQL files fetched from a remote location (e.g., HTTP).
 //!     See the [`remote`] module documentation for details and usage examples.
 //!     Requires disabling default features and adding `reqwest` and `serde` as dependencies.
+//! *   **`embed`**: Migrates using SQL files embedded into the binary at compile time by
+//!     the [`embed_migrations!`] macro. See the [`embed`] module documentation.
 //!
 //! Enable features in your `Cargo.toml`:
 //!
@@ -69,6 +78,9 @@
 //! # libsql_migration = { version = "...", default-features = false, features = ["dir", "remote"] } # Explicit
 //! ```
 //!
+//! Every source is a [`migrator::MigrationSource`] applied by [`migrator::Migrator`].
+//! Implement the trait to plug in sources of your own.
+//!
 //! ## Example Usage (using default `dir` feature)
 //!
 //! For detailed examples of each feature, please refer to the respective module documentation
@@ -78,6 +90,10 @@
 //! [GitHub Repository](https://github.com/prashant1k99/libsql_migration)
 
 pub mod errors;
+#[cfg(any(feature = "dir", feature = "embed"))]
+mod files;
+pub mod migrator;
+mod statement;
 pub mod util;
 
 #[cfg(feature = "content")]
@@ -88,3 +104,10 @@ pub mod remote;
 
 #[cfg(feature = "dir")]
 pub mod dir;
+
+#[cfg(feature = "embed")]
+pub mod embed;
+
+/// Embeds a migrations directory into the binary. See the [`embed`] module.
+#[cfg(feature = "embed")]
+pub use libsql_migration_macros::embed_migrations;
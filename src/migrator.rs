@@ -0,0 +1,287 @@
+//! Runs migrations from any [`MigrationSource`].
+//!
+//! Every source module of this crate (`dir`, `content`, `remote`, `embed`)
+//! implements [`MigrationSource`] and is run by [`Migrator`]. Implement the trait
+//! to apply migrations kept anywhere else while reusing the tracking, locking and
+//! error handling of the crate.
+//!
+//! # Usage
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! use libsql_migration::{
+//!     errors::LibsqlMigratorBaseError,
+//!     migrator::{MigrationSource, Migrator},
+//!     util::Migration,
+//! };
+//! use libsql::Builder;
+//!
+//! struct StaticSource;
+//!
+//! impl MigrationSource for StaticSource {
+//!     type Error = LibsqlMigratorBaseError;
+//!
+//!     async fn migrations(&self) -> Result<Vec<Migration>, Self::Error> {
+//!         Ok(vec![Migration::new(
+//!             "0001_users".to_string(),
+//!             "CREATE TABLE users (id INTEGER PRIMARY KEY);".to_string(),
+//!             Some("DROP TABLE users;".to_string()),
+//!         )])
+//!     }
+//! }
+//!
+//! let db = Builder::new_local("my_database.db").build().await.unwrap();
+//! let conn = db.connect().unwrap();
+//!
+//! let report = Migrator::new(StaticSource).migrate(&conn).await?;
+//! println!("Applied {:?}", report.applied);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{future::Future, time::Instant};
+
+use libsql::Connection;
+
+use crate::errors::LibsqlMigratorBaseError;
+use crate::util::{
+    FailedMigration, MigrateOptions, Migration, MigrationFuture, MigrationReport, MigrationStatus,
+    OrderingStrategy, PlannedMigration, applied_ids, compare_ids, migration_status,
+    plan_migrations, query_failed_migrations, rollback_migrations, run_migrations,
+    verify_migrations,
+};
+
+/// Lists the migrations [`Migrator`] applies.
+pub trait MigrationSource {
+    /// Error returned by the source. Errors of the crate are converted into it.
+    type Error: From<LibsqlMigratorBaseError>;
+
+    /// Every migration of the source, in the order they must be applied.
+    ///
+    /// Ids must be unique. Migrations that are already applied are listed too;
+    /// the runner skips them. With a prefix [`MigrateOptions::strategy`], the
+    /// runner sorts them by version instead.
+    fn migrations(&self) -> impl Future<Output = Result<Vec<Migration>, Self::Error>> + Send;
+
+    /// Same as [`migrations`](Self::migrations), used by [`Migrator::migrate`].
+    ///
+    /// Migrations whose id is in `applied` may be listed with [`Migration::applied`]
+    /// instead, so sources where loading a script is expensive can skip it.
+    fn pending_migrations(
+        &self,
+        applied: &[String],
+    ) -> impl Future<Output = Result<Vec<Migration>, Self::Error>> + Send {
+        let _ = applied;
+        self.migrations()
+    }
+
+    /// Combines this source with `other`, interleaving their migrations by id.
+    fn merge<B>(self, other: B) -> Merged<Self, B>
+    where
+        Self: Sized,
+    {
+        Merged::new(self, other)
+    }
+}
+
+/// The migrations of two sources, interleaved by id.
+///
+/// Ids are compared by their last path segment, then in full, so files of a
+/// [`DirSource`](crate::dir::DirSource) keep their order and a migration with id
+/// `0002_backfill` runs between `0001_init.sql` and `0003_index.sql`.
+#[derive(Debug, Clone)]
+pub struct Merged<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> Merged<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<A, B> MigrationSource for Merged<A, B>
+where
+    A: MigrationSource + Sync,
+    B: MigrationSource + Sync,
+    A::Error: From<B::Error>,
+{
+    type Error = A::Error;
+
+    async fn migrations(&self) -> Result<Vec<Migration>, Self::Error> {
+        let mut migrations = self.first.migrations().await?;
+        migrations.extend(self.second.migrations().await?);
+
+        migrations.sort_by(|a, b| compare_ids(&a.id, &b.id));
+
+        Ok(migrations)
+    }
+
+    async fn pending_migrations(&self, applied: &[String]) -> Result<Vec<Migration>, Self::Error> {
+        let mut migrations = self.first.pending_migrations(applied).await?;
+        migrations.extend(self.second.pending_migrations(applied).await?);
+
+        migrations.sort_by(|a, b| compare_ids(&a.id, &b.id));
+
+        Ok(migrations)
+    }
+}
+
+/// Migrations written in Rust, built with [`Migration::from_fn`].
+///
+/// Applied in the order they were added. Combine them with SQL migrations through
+/// [`MigrationSource::merge`].
+#[derive(Debug, Clone, Default)]
+pub struct CodeMigrations {
+    migrations: Vec<Migration>,
+}
+
+impl CodeMigrations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a migration with id `id` running `f`. See [`Migration::from_fn`].
+    pub fn add<F>(mut self, id: impl Into<String>, f: F) -> Self
+    where
+        F: for<'c> Fn(&'c Connection) -> MigrationFuture<'c> + Send + Sync + 'static,
+    {
+        self.migrations.push(Migration::from_fn(id, f));
+        self
+    }
+}
+
+impl MigrationSource for CodeMigrations {
+    type Error = LibsqlMigratorBaseError;
+
+    async fn migrations(&self) -> Result<Vec<Migration>, Self::Error> {
+        Ok(self.migrations.clone())
+    }
+}
+
+/// Applies the migrations of a [`MigrationSource`] and tracks them in the table
+/// configured by its [`MigrateOptions`].
+#[derive(Debug, Clone)]
+pub struct Migrator<S> {
+    source: S,
+    options: MigrateOptions,
+}
+
+impl<S: MigrationSource> Migrator<S> {
+    pub fn new(source: S) -> Self {
+        Self {
+            source,
+            options: MigrateOptions::default(),
+        }
+    }
+
+    pub fn with_options(mut self, options: MigrateOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    pub fn options(&self) -> &MigrateOptions {
+        &self.options
+    }
+
+    async fn migrations(&self) -> Result<Vec<Migration>, S::Error> {
+        self.order(self.source.migrations().await?)
+    }
+
+    /// Sorts `migrations` with [`MigrateOptions::strategy`], keeping the order of
+    /// the source for [`OrderingStrategy::Lexicographic`].
+    fn order(&self, mut migrations: Vec<Migration>) -> Result<Vec<Migration>, S::Error> {
+        check_unique_ids(&migrations)?;
+
+        if self.options.strategy != OrderingStrategy::Lexicographic {
+            self.options
+                .strategy
+                .sort(&mut migrations, |migration| &migration.id)?;
+        }
+
+        Ok(migrations)
+    }
+
+    /// Applies the pending migrations of the source, in order.
+    ///
+    /// Only the migrations that are not applied yet are loaded, see
+    /// [`MigrationSource::pending_migrations`]. With [`MigrateOptions::dry_run`]
+    /// set, nothing is executed and the report lists the migrations that would be
+    /// applied.
+    pub async fn migrate(&self, conn: &Connection) -> Result<MigrationReport, S::Error> {
+        let started = Instant::now();
+
+        let applied = applied_ids(conn, &self.options.table).await?;
+        let migrations = self.order(self.source.pending_migrations(&applied).await?)?;
+
+        Ok(run_migrations(conn, migrations, &self.options, started).await?)
+    }
+
+    /// Lists the migrations [`migrate`](Self::migrate) would apply, in the order it
+    /// would apply them, without executing anything.
+    pub async fn plan(&self, conn: &Connection) -> Result<Vec<PlannedMigration>, S::Error> {
+        let migrations = self.migrations().await?;
+
+        Ok(plan_migrations(conn, &self.options.table, migrations).await?)
+    }
+
+    /// Checks that no already applied migration has changed since it was applied.
+    ///
+    /// Fails with [`LibsqlMigratorBaseError::ChecksumMismatch`] listing the
+    /// modified ids.
+    pub async fn verify(&self, conn: &Connection) -> Result<(), S::Error> {
+        let migrations = self.migrations().await?;
+
+        verify_migrations(conn, &self.options.table, &migrations).await?;
+
+        Ok(())
+    }
+
+    /// Reports the state of every migration of the source, plus applied migrations
+    /// the source no longer lists, without applying anything.
+    pub async fn status(&self, conn: &Connection) -> Result<Vec<MigrationStatus>, S::Error> {
+        let migrations = self.migrations().await?;
+
+        Ok(migration_status(conn, &self.options.table, &migrations).await?)
+    }
+
+    /// Reverts every migration applied after `target_id`, newest first, or every
+    /// applied migration when `target_id` is `None`. Returns the reverted ids.
+    pub async fn rollback(
+        &self,
+        conn: &Connection,
+        target_id: Option<String>,
+    ) -> Result<Vec<String>, S::Error> {
+        Ok(rollback_migrations(conn, &self.options, target_id).await?)
+    }
+
+    /// Lists the migrations whose last attempt failed.
+    pub async fn failed_migrations(
+        &self,
+        conn: &Connection,
+    ) -> Result<Vec<FailedMigration>, S::Error> {
+        Ok(query_failed_migrations(conn, &self.options.table).await?)
+    }
+}
+
+/// Fails with [`LibsqlMigratorBaseError::DuplicateMigrationId`] when two
+/// migrations share an id.
+fn check_unique_ids(migrations: &[Migration]) -> Result<(), LibsqlMigratorBaseError> {
+    for (index, migration) in migrations.iter().enumerate() {
+        if migrations[..index]
+            .iter()
+            .any(|other| other.id == migration.id)
+        {
+            return Err(LibsqlMigratorBaseError::DuplicateMigrationId(
+                migration.id.clone(),
+            ));
+        }
+    }
+
+    Ok(())
+}
@@ -2,8 +2,12 @@
 //!
 //! This module is activated by the `remote` feature. It expects a URL pointing to
 //! a JSON endpoint that returns a list of migration objects, each containing an `id`
-//! and a `url` pointing to the actual SQL script. Migrations are sorted by `id`
-//! and applied sequentially.
+//! and either a `url` pointing to the actual SQL script or the script itself as `sql`.
+//! Relative URLs are resolved against the manifest URL. Migrations are sorted by `id`
+//! (see [`MigrateOptions::strategy`]) and applied sequentially, after every pending
+//! script has been fetched. An optional `down_url`
+//! (or inline `down_sql`) gives a script that reverts the migration; it is stored when
+//! the migration is applied and used by [`rollback`].
 //!
 //! # Usage
 //!
@@ -25,17 +29,21 @@
 //! // URL pointing to a JSON array like:
 //! // [
 //! //   { "id": "0001_remote_init", "url": "http://example.com/migrations/0001.sql" },
-//! //   { "id": "0002_remote_users", "url": "http://example.com/migrations/0002.sql" }
+//! //   { "id": "0002_remote_users", "url": "0002.sql" },
+//! //   { "id": "0003_remote_index", "sql": "CREATE INDEX idx_users_name ON users (name);" }
 //! // ]
 //! let remote_migrations_url = "http://example.com/migrations.json".to_string();
 //!
 //! match migrate(&conn, remote_migrations_url).await {
-//!     Ok(applied) => {
-//!         if applied {
-//!             println!("Remote migrations applied successfully.");
-//!         } else {
-//!             println!("No new remote migrations to apply.");
+//!     Ok(report) => {
+//!         for id in &report.applied {
+//!             println!("Applied {} in {:?}", id, report.durations[id]);
 //!         }
+//!         println!(
+//!             "Remote migrations done in {:?} ({} already applied)",
+//!             report.total_duration,
+//!             report.skipped.len()
+//!         );
 //!     }
 //!     Err(e) => eprintln!("Remote migration failed: {}", e),
 //! }
@@ -44,51 +52,571 @@
 //! ```
 
 use crate::errors::LibsqlRemoteMigratorError;
-use crate::util::{MigrationResult, create_migration_table, execute_migration};
+use crate::migrator::{MigrationSource, Migrator};
+use crate::util::{
+    FailedMigration, MigrateOptions, Migration, MigrationReport, MigrationStatus, PlannedMigration,
+    checksum, compare_ids, query_failed_migrations, rollback_migrations,
+};
+use futures_util::{StreamExt, TryStreamExt, stream};
 use libsql::Connection;
+use reqwest::StatusCode;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::{future::Future, time::Duration};
 
 #[derive(serde::Deserialize, Debug)]
 struct RemoteMigrationFileSchema {
     id: String,
-    url: String,
+    url: Option<String>,
+    /// The script itself, instead of a `url` to fetch it from.
+    sql: Option<String>,
+    down_url: Option<String>,
+    down_sql: Option<String>,
+    /// Hex encoded SHA-256 of the script.
+    sha256: Option<String>,
+    /// Hex encoded SHA-256 of the down script.
+    down_sha256: Option<String>,
 }
 
-async fn make_request(
-    url: String,
-) -> Result<Vec<RemoteMigrationFileSchema>, LibsqlRemoteMigratorError> {
-    let mut files = reqwest::get(url)
-        .await?
-        .json::<Vec<RemoteMigrationFileSchema>>()
-        .await?;
+impl RemoteMigrationFileSchema {
+    /// Fails with [`LibsqlRemoteMigratorError::InvalidManifest`] unless the entry has
+    /// exactly one of `url` and `sql`, and at most one of `down_url` and `down_sql`.
+    fn validate(&self) -> Result<(), LibsqlRemoteMigratorError> {
+        let reason = match (&self.url, &self.sql, &self.down_url, &self.down_sql) {
+            (Some(_), Some(_), _, _) => "has both a url and inline sql",
+            (None, None, _, _) => "has neither a url nor inline sql",
+            (_, _, Some(_), Some(_)) => "has both a down_url and inline down_sql",
+            _ => return Ok(()),
+        };
 
-    files.sort_by(|a, b| a.id.cmp(&b.id));
+        Err(LibsqlRemoteMigratorError::InvalidManifest(format!(
+            "migration {} {}",
+            self.id, reason
+        )))
+    }
+}
 
-    Ok(files)
+/// Key verifying the signature of a manifest, see [`RemoteSource::with_manifest_key`].
+pub use ed25519_dalek::VerifyingKey;
+
+/// Credentials sent with every request of a [`RemoteSource`].
+#[derive(Debug, Clone)]
+enum Auth {
+    Bearer(String),
+    Basic(String, Option<String>),
 }
 
-async fn get_file_content(url: String) -> Result<String, LibsqlRemoteMigratorError> {
-    let content = reqwest::get(url).await?.text().await?;
+/// Fails with [`LibsqlRemoteMigratorError::IntegrityMismatch`] when `expected`
+/// is set and is not the SHA-256 of `content`.
+fn verify_integrity(
+    id: &str,
+    url: &str,
+    expected: Option<&str>,
+    content: &str,
+) -> Result<(), LibsqlRemoteMigratorError> {
+    let Some(expected) = expected else {
+        return Ok(());
+    };
+
+    let actual = checksum(content);
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        return Err(LibsqlRemoteMigratorError::IntegrityMismatch {
+            id: id.to_string(),
+            url: url.to_string(),
+            expected: expected.to_string(),
+            actual,
+        });
+    }
 
-    Ok(content)
+    Ok(())
+}
+
+/// Checks the hex encoded `signature` of `manifest`, returning why it is invalid.
+fn verify_signature(key: &VerifyingKey, manifest: &[u8], signature: &str) -> Result<(), String> {
+    let signature = signature.trim();
+    let bytes: Vec<u8> = (0..signature.len())
+        .step_by(2)
+        .map(|i| {
+            signature
+                .get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+        })
+        .collect::<Option<_>>()
+        .ok_or("signature is not hex encoded")?;
+    let bytes: [u8; 64] = bytes
+        .try_into()
+        .map_err(|_| "signature is not 64 bytes long")?;
+
+    key.verify_strict(manifest, &ed25519_dalek::Signature::from_bytes(&bytes))
+        .map_err(|e| e.to_string())
+}
+
+/// The migrations listed by a remote JSON manifest as a [`MigrationSource`].
+///
+/// The client, headers and credentials configured here are used for the manifest
+/// and for every script it lists.
+///
+/// ```no_run
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// use libsql_migration::{migrator::Migrator, remote::RemoteSource};
+/// use reqwest::header::{HeaderName, HeaderValue};
+///
+/// let source = RemoteSource::new("https://artifacts.example.com/migrations.json")
+///     .with_bearer_token("secret")
+///     .with_header(
+///         HeaderName::from_static("x-api-key"),
+///         HeaderValue::from_static("key"),
+///     );
+///
+/// let db = libsql::Builder::new_local("my_database.db").build().await?;
+/// Migrator::new(source).migrate(&db.connect()?).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RemoteSource {
+    url: String,
+    client: reqwest::Client,
+    headers: HeaderMap,
+    auth: Option<Auth>,
+    timeout: Option<Duration>,
+    retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    manifest_key: Option<VerifyingKey>,
+    signature_url: Option<String>,
+    concurrency: usize,
 }
 
-pub async fn migrate(conn: &Connection, url: String) -> Result<bool, LibsqlRemoteMigratorError> {
-    if url.is_empty() {
-        return Err(LibsqlRemoteMigratorError::MigrationUrlNotValid(url));
+impl RemoteSource {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            client: reqwest::Client::new(),
+            headers: HeaderMap::new(),
+            auth: None,
+            timeout: Some(Duration::from_secs(30)),
+            retries: 3,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(5),
+            manifest_key: None,
+            signature_url: None,
+            concurrency: 8,
+        }
+    }
+
+    /// Sends the requests with `client`, keeping its default headers, proxy and TLS
+    /// configuration.
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Adds a header to every request, replacing a previous value of `name`.
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Adds `headers` to every request.
+    pub fn with_headers(mut self, headers: HeaderMap) -> Self {
+        self.headers.extend(headers);
+        self
+    }
+
+    /// Sends `Authorization: Bearer <token>` with every request.
+    pub fn with_bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.auth = Some(Auth::Bearer(token.into()));
+        self
+    }
+
+    /// Sends HTTP basic authentication with every request.
+    pub fn with_basic_auth(
+        mut self,
+        username: impl Into<String>,
+        password: Option<impl Into<String>>,
+    ) -> Self {
+        self.auth = Some(Auth::Basic(username.into(), password.map(Into::into)));
+        self
+    }
+
+    /// Fails a request, including reading its body, after `timeout`, or never with
+    /// `None`. Defaults to 30 seconds.
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Retries a request up to `retries` times after a connection error, a timeout,
+    /// a `429` or a `5xx` response. Defaults to 3.
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Waits `initial` before the first retry, doubling the delay for each
+    /// following one up to `max`. Defaults to 250 milliseconds and 5 seconds.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Requires the manifest to be signed with the private key of `key`.
+    ///
+    /// The hex encoded ed25519 signature of the manifest bytes is fetched from the
+    /// manifest URL followed by `.sig`, and every entry with a `url` must then carry
+    /// a `sha256` (and a `down_sha256` with a `down_url`) so the fetched scripts are
+    /// covered by the signature too. Inline scripts are signed with the manifest.
+    pub fn with_manifest_key(mut self, key: VerifyingKey) -> Self {
+        self.manifest_key = Some(key);
+        self
+    }
+
+    /// Fetches the manifest signature from `url` instead of the manifest URL
+    /// followed by `.sig`.
+    pub fn with_signature_url(mut self, url: impl Into<String>) -> Self {
+        self.signature_url = Some(url.into());
+        self
+    }
+
+    /// Fetches up to `concurrency` scripts at a time. Defaults to 8.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    fn request(&self, url: &str) -> reqwest::RequestBuilder {
+        let mut request = self.client.get(url).headers(self.headers.clone());
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+
+        match &self.auth {
+            Some(Auth::Bearer(token)) => request.bearer_auth(token),
+            Some(Auth::Basic(username, password)) => {
+                request.basic_auth(username, password.as_ref())
+            }
+            None => request,
+        }
+    }
+
+    /// Sends a request to `url` and reads its body with `read`, retrying as
+    /// configured. A response other than `2xx` fails with
+    /// [`LibsqlRemoteMigratorError::UnexpectedStatus`].
+    async fn fetch<T, F, Fut>(&self, url: &str, read: F) -> Result<T, LibsqlRemoteMigratorError>
+    where
+        F: Fn(reqwest::Response) -> Fut,
+        Fut: Future<Output = Result<T, reqwest::Error>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let error = match self.request(url).send().await {
+                Ok(response) if response.status().is_success() => match read(response).await {
+                    Ok(body) => return Ok(body),
+                    Err(e) if e.is_timeout() || e.is_body() => e.into(),
+                    Err(e) => return Err(e.into()),
+                },
+                Ok(response) => {
+                    let status = response.status();
+                    let error = LibsqlRemoteMigratorError::UnexpectedStatus {
+                        url: url.to_string(),
+                        status: status.as_u16(),
+                    };
+                    if !(status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS) {
+                        return Err(error);
+                    }
+                    error
+                }
+                Err(e) if e.is_timeout() || e.is_connect() || e.is_request() => e.into(),
+                Err(e) => return Err(e.into()),
+            };
+
+            if attempt >= self.retries {
+                return Err(error);
+            }
+
+            let backoff = self
+                .initial_backoff
+                .saturating_mul(2u32.saturating_pow(attempt))
+                .min(self.max_backoff);
+            tokio::time::sleep(backoff).await;
+            attempt += 1;
+        }
+    }
+
+    async fn make_request(
+        &self,
+    ) -> Result<Vec<RemoteMigrationFileSchema>, LibsqlRemoteMigratorError> {
+        let mut files = match &self.manifest_key {
+            None => {
+                self.fetch(&self.url, |response| {
+                    response.json::<Vec<RemoteMigrationFileSchema>>()
+                })
+                .await?
+            }
+            Some(key) => self.fetch_signed_manifest(key).await?,
+        };
+
+        for file in &files {
+            file.validate()?;
+        }
+        files.sort_by(|a, b| compare_ids(&a.id, &b.id));
+
+        Ok(files)
+    }
+
+    async fn fetch_signed_manifest(
+        &self,
+        key: &VerifyingKey,
+    ) -> Result<Vec<RemoteMigrationFileSchema>, LibsqlRemoteMigratorError> {
+        let manifest = self.fetch(&self.url, |response| response.bytes()).await?;
+
+        let signature_url = self
+            .signature_url
+            .clone()
+            .unwrap_or_else(|| format!("{}.sig", self.url));
+        let signature = self
+            .fetch(&signature_url, |response| response.text())
+            .await?;
+
+        verify_signature(key, &manifest, &signature).map_err(|reason| {
+            LibsqlRemoteMigratorError::InvalidManifestSignature {
+                url: self.url.clone(),
+                reason,
+            }
+        })?;
+
+        let files: Vec<RemoteMigrationFileSchema> = serde_json::from_slice(&manifest)
+            .map_err(|e| LibsqlRemoteMigratorError::InvalidManifest(e.to_string()))?;
+
+        if let Some(file) = files.iter().find(|file| {
+            (file.url.is_some() && file.sha256.is_none())
+                || (file.down_url.is_some() && file.down_sha256.is_none())
+        }) {
+            return Err(LibsqlRemoteMigratorError::MissingIntegrityHash(
+                file.id.clone(),
+            ));
+        }
+
+        Ok(files)
     }
 
-    create_migration_table(conn).await?;
+    /// Resolves `url` against the manifest URL, leaving absolute URLs unchanged.
+    fn resolve(&self, url: &str) -> Result<String, LibsqlRemoteMigratorError> {
+        reqwest::Url::parse(&self.url)
+            .and_then(|base| base.join(url))
+            .map(String::from)
+            .map_err(|_| LibsqlRemoteMigratorError::MigrationUrlNotValid(url.to_string()))
+    }
+
+    async fn get_file_content(&self, url: &str) -> Result<String, LibsqlRemoteMigratorError> {
+        self.fetch(url, |response| response.text()).await
+    }
+
+    /// Returns the inline `sql`, or else the script fetched from `url`, checked
+    /// against `expected`.
+    async fn get_script(
+        &self,
+        id: &str,
+        url: Option<&str>,
+        sql: Option<String>,
+        expected: Option<&str>,
+    ) -> Result<Option<String>, LibsqlRemoteMigratorError> {
+        let (url, script) = match (sql, url) {
+            (Some(sql), _) => (self.url.clone(), sql),
+            (None, Some(url)) => {
+                let url = self.resolve(url)?;
+                let script = self.get_file_content(&url).await?;
+                (url, script)
+            }
+            (None, None) => return Ok(None),
+        };
+
+        verify_integrity(id, &url, expected, &script)?;
+        Ok(Some(script))
+    }
 
-    let all_files = make_request(url).await?;
+    async fn get_migration(
+        &self,
+        file: RemoteMigrationFileSchema,
+    ) -> Result<Migration, LibsqlRemoteMigratorError> {
+        let script = self
+            .get_script(
+                &file.id,
+                file.url.as_deref(),
+                file.sql,
+                file.sha256.as_deref(),
+            )
+            .await?
+            .unwrap_or_default();
+        let down_script = self
+            .get_script(
+                &file.id,
+                file.down_url.as_deref(),
+                file.down_sql,
+                file.down_sha256.as_deref(),
+            )
+            .await?;
 
-    let mut did_new_migration = false;
+        Ok(Migration::new(file.id, script, down_script))
+    }
 
-    for file in all_files {
-        let content = get_file_content(file.url).await?;
-        if let MigrationResult::Executed = execute_migration(conn, file.id, content).await? {
-            did_new_migration = true
+    /// Fetches the manifest, then the scripts of the entries not in `applied`
+    /// concurrently, failing before returning anything if one of them fails.
+    async fn load(&self, applied: &[String]) -> Result<Vec<Migration>, LibsqlRemoteMigratorError> {
+        if self.url.is_empty() {
+            return Err(LibsqlRemoteMigratorError::MigrationUrlNotValid(
+                self.url.clone(),
+            ));
         }
+
+        let files = self.make_request().await?;
+
+        stream::iter(files)
+            .map(|file| async move {
+                if applied.contains(&file.id) {
+                    Ok(Migration::applied(file.id))
+                } else {
+                    self.get_migration(file).await
+                }
+            })
+            .buffered(self.concurrency)
+            .try_collect()
+            .await
+    }
+}
+
+impl MigrationSource for RemoteSource {
+    type Error = LibsqlRemoteMigratorError;
+
+    async fn migrations(&self) -> Result<Vec<Migration>, Self::Error> {
+        self.load(&[]).await
     }
 
-    Ok(did_new_migration)
+    /// Fetches the scripts of the entries that are not in `applied` only.
+    async fn pending_migrations(&self, applied: &[String]) -> Result<Vec<Migration>, Self::Error> {
+        self.load(applied).await
+    }
+}
+
+pub async fn migrate(
+    conn: &Connection,
+    url: String,
+) -> Result<MigrationReport, LibsqlRemoteMigratorError> {
+    migrate_with_options(conn, url, &MigrateOptions::default()).await
+}
+
+/// Same as [`migrate`], configured by `options`.
+///
+/// With [`MigrateOptions::dry_run`] set, the manifest and scripts are fetched but
+/// nothing is executed, and the report lists the migrations that would be applied.
+pub async fn migrate_with_options(
+    conn: &Connection,
+    url: String,
+    options: &MigrateOptions,
+) -> Result<MigrationReport, LibsqlRemoteMigratorError> {
+    Migrator::new(RemoteSource::new(url))
+        .with_options(options.clone())
+        .migrate(conn)
+        .await
+}
+
+/// Lists the migrations listed at `url` that [`migrate`] would apply, in the
+/// order it would apply them, without executing anything.
+pub async fn plan(
+    conn: &Connection,
+    url: String,
+) -> Result<Vec<PlannedMigration>, LibsqlRemoteMigratorError> {
+    plan_with_options(conn, url, &MigrateOptions::default()).await
+}
+
+/// Same as [`plan`], configured by `options`.
+pub async fn plan_with_options(
+    conn: &Connection,
+    url: String,
+    options: &MigrateOptions,
+) -> Result<Vec<PlannedMigration>, LibsqlRemoteMigratorError> {
+    Migrator::new(RemoteSource::new(url))
+        .with_options(options.clone())
+        .plan(conn)
+        .await
+}
+
+/// Checks that no already applied migration listed at `url` has changed since it
+/// was applied.
+///
+/// Returns [`LibsqlMigratorBaseError::ChecksumMismatch`](crate::errors::LibsqlMigratorBaseError::ChecksumMismatch)
+/// (wrapped in [`LibsqlRemoteMigratorError::BaseError`]) listing the modified ids.
+pub async fn verify(conn: &Connection, url: String) -> Result<(), LibsqlRemoteMigratorError> {
+    verify_with_options(conn, url, &MigrateOptions::default()).await
+}
+
+/// Same as [`verify`], configured by `options`.
+pub async fn verify_with_options(
+    conn: &Connection,
+    url: String,
+    options: &MigrateOptions,
+) -> Result<(), LibsqlRemoteMigratorError> {
+    Migrator::new(RemoteSource::new(url))
+        .with_options(options.clone())
+        .verify(conn)
+        .await
+}
+
+/// Reports the state of every migration listed at `url`, plus applied migrations
+/// that are no longer listed, without applying anything.
+pub async fn status(
+    conn: &Connection,
+    url: String,
+) -> Result<Vec<MigrationStatus>, LibsqlRemoteMigratorError> {
+    status_with_options(conn, url, &MigrateOptions::default()).await
+}
+
+/// Same as [`status`], configured by `options`.
+pub async fn status_with_options(
+    conn: &Connection,
+    url: String,
+    options: &MigrateOptions,
+) -> Result<Vec<MigrationStatus>, LibsqlRemoteMigratorError> {
+    Migrator::new(RemoteSource::new(url))
+        .with_options(options.clone())
+        .status(conn)
+        .await
+}
+
+/// Reverts every migration applied after `target_id`, newest first, or every
+/// applied migration when `target_id` is `None`.
+///
+/// Uses the scripts fetched from each migration's `down_url` when it was applied and
+/// removes the reverted migrations from `libsql_migrations`. Returns the reverted ids.
+pub async fn rollback(
+    conn: &Connection,
+    target_id: Option<String>,
+) -> Result<Vec<String>, LibsqlRemoteMigratorError> {
+    rollback_with_options(conn, target_id, &MigrateOptions::default()).await
+}
+
+/// Same as [`rollback`], configured by `options`.
+pub async fn rollback_with_options(
+    conn: &Connection,
+    target_id: Option<String>,
+    options: &MigrateOptions,
+) -> Result<Vec<String>, LibsqlRemoteMigratorError> {
+    Ok(rollback_migrations(conn, options, target_id).await?)
+}
+
+/// Lists the migrations whose last attempt failed, with the recorded error, the
+/// failing statement, the number of attempts and the time of the last attempt.
+pub async fn failed_migrations(
+    conn: &Connection,
+) -> Result<Vec<FailedMigration>, LibsqlRemoteMigratorError> {
+    failed_migrations_with_options(conn, &MigrateOptions::default()).await
+}
+
+/// Same as [`failed_migrations`], configured by `options`.
+pub async fn failed_migrations_with_options(
+    conn: &Connection,
+    options: &MigrateOptions,
+) -> Result<Vec<FailedMigration>, LibsqlRemoteMigratorError> {
+    Ok(query_failed_migrations(conn, &options.table).await?)
 }
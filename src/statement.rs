@@ -0,0 +1,100 @@
+/// Splits a migration script into its individual SQL statements.
+///
+/// Statements are separated by `;`, except where the semicolon appears inside
+/// a string literal, a quoted identifier, a comment or the `BEGIN ... END`
+/// body of a `CREATE TRIGGER` statement. Each returned statement keeps its
+/// terminating semicolon; empty statements and trailing comments are dropped.
+pub(crate) fn split_statements(sql: &str) -> Vec<&str> {
+    let bytes = sql.as_bytes();
+    let mut statements = vec![];
+
+    let mut start = 0;
+    let mut i = 0;
+    let mut has_tokens = false;
+    let mut leading_words: Vec<String> = vec![];
+    let mut is_trigger = false;
+    let mut block_depth = 0usize;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'\'' | b'"' | b'`') => {
+                has_tokens = true;
+                i = skip_past(bytes, i + 1, &[quote]);
+            }
+            b'[' => {
+                has_tokens = true;
+                i = skip_past(bytes, i + 1, b"]");
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                i = skip_past(bytes, i + 2, b"\n");
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = skip_past(bytes, i + 2, b"*/");
+            }
+            b';' if block_depth == 0 => {
+                if has_tokens {
+                    statements.push(sql[start..=i].trim());
+                }
+                i += 1;
+                start = i;
+                has_tokens = false;
+                leading_words.clear();
+                is_trigger = false;
+            }
+            byte if is_word_byte(byte) => {
+                has_tokens = true;
+                let word_start = i;
+                while i < bytes.len() && is_word_byte(bytes[i]) {
+                    i += 1;
+                }
+                let word = sql[word_start..i].to_ascii_uppercase();
+
+                if leading_words.len() < 3 && !is_trigger {
+                    leading_words.push(word.clone());
+                    is_trigger = matches!(
+                        leading_words
+                            .iter()
+                            .map(String::as_str)
+                            .collect::<Vec<_>>()
+                            .as_slice(),
+                        ["CREATE", "TRIGGER"] | ["CREATE", "TEMP" | "TEMPORARY", "TRIGGER"]
+                    );
+                }
+
+                if is_trigger {
+                    match word.as_str() {
+                        "BEGIN" => block_depth += 1,
+                        "CASE" if block_depth > 0 => block_depth += 1,
+                        "END" => block_depth = block_depth.saturating_sub(1),
+                        _ => {}
+                    }
+                }
+            }
+            byte => {
+                if !byte.is_ascii_whitespace() {
+                    has_tokens = true;
+                }
+                i += 1;
+            }
+        }
+    }
+
+    if has_tokens {
+        statements.push(sql[start..].trim());
+    }
+
+    statements
+}
+
+fn is_word_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$' || !byte.is_ascii()
+}
+
+/// Returns the index right after the next occurrence of `terminator`
+/// starting at `from`, or the end of input if it never occurs.
+fn skip_past(bytes: &[u8], from: usize, terminator: &[u8]) -> usize {
+    bytes[from.min(bytes.len())..]
+        .windows(terminator.len())
+        .position(|window| window == terminator)
+        .map_or(bytes.len(), |pos| from + pos + terminator.len())
+}
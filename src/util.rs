@@ -1,69 +1,1384 @@
-use std::path::Path;
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    error::Error,
+    fmt,
+    future::Future,
+    path::Path,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use crate::errors::{LibsqlDirMigratorError, LibsqlMigratorBaseError};
+use crate::errors::LibsqlMigratorBaseError;
+use crate::statement::split_statements;
 use libsql::Connection;
+use sha2::{Digest, Sha256};
 
-pub(crate) async fn create_migration_table(
+/// Name and schema of the table that tracks applied migrations.
+///
+/// Defaults to `libsql_migrations` in the connection's default schema. Use a
+/// different name to keep independent sets of migrations in one database, and a
+/// schema to keep the table in an attached database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationTable {
+    pub name: String,
+    pub schema: Option<String>,
+}
+
+impl Default for MigrationTable {
+    fn default() -> Self {
+        Self::new("libsql_migrations")
+    }
+}
+
+impl MigrationTable {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            schema: None,
+        }
+    }
+
+    /// Places the table in the attached database `schema`.
+    pub fn with_schema(mut self, schema: impl Into<String>) -> Self {
+        self.schema = Some(schema.into());
+        self
+    }
+
+    /// Quoted, schema qualified name usable in SQL.
+    pub(crate) fn qualified_name(&self) -> String {
+        format!("{}{}", self.schema_prefix(), quote_identifier(&self.name))
+    }
+
+    fn schema_prefix(&self) -> String {
+        self.schema
+            .as_ref()
+            .map(|schema| format!("{}.", quote_identifier(schema)))
+            .unwrap_or_default()
+    }
+
+    /// Name of the companion table recording the layout version of this table.
+    pub(crate) fn version_table_name(&self) -> String {
+        format!("{}_version", self.name)
+    }
+
+    /// Qualified name of the `<name>_<suffix>` table kept next to this table.
+    fn companion_table(&self, suffix: &str) -> String {
+        format!(
+            "{}{}",
+            self.schema_prefix(),
+            quote_identifier(&format!("{}_{}", self.name, suffix))
+        )
+    }
+
+    /// Substitutes `{table}` (qualified name), `{version_table}` and
+    /// `{lock_table}` (qualified names of the companion tables), `{name}` (quoted
+    /// name) and `{schema}` (quoted schema followed by `.`, or nothing) in `query`.
+    pub(crate) fn sql(&self, query: &str) -> String {
+        query
+            .replace("{table}", &self.qualified_name())
+            .replace("{version_table}", &self.companion_table("version"))
+            .replace("{lock_table}", &self.companion_table("lock"))
+            .replace("{name}", &quote_identifier(&self.name))
+            .replace("{schema}", &self.schema_prefix())
+    }
+}
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// Columns added to the tracking table by each version after the first, which is
+/// the 3-column layout of `base_migration_table.sql` created by 0.2.x releases.
+const TABLE_UPGRADES: &[&[(&str, &str)]] = &[
+    &[("checksum", "TEXT")],
+    &[("down_script", "TEXT")],
+    &[
+        ("error_message", "TEXT"),
+        ("failed_statement", "TEXT"),
+        ("attempts", "INTEGER DEFAULT 0"),
+        ("last_attempt_at", "DATE"),
+    ],
+];
+
+/// Version of the tracking table layout written by this release.
+pub const MIGRATION_TABLE_VERSION: i64 = 1 + TABLE_UPGRADES.len() as i64;
+
+/// Creates the tracking table and brings it up to [`MIGRATION_TABLE_VERSION`].
+///
+/// The version is stored in a companion `<name>_version` table. Tables without one
+/// were created by a release that did not version them and are upgraded from the
+/// first layout; columns that already exist are kept.
+async fn create_migration_table(
     conn: &Connection,
+    table: &MigrationTable,
 ) -> Result<(), LibsqlMigratorBaseError> {
-    let sql_query = include_str!("./base_migration_table.sql");
+    let version = table_version(conn, table).await?;
+    if version == Some(MIGRATION_TABLE_VERSION) {
+        return Ok(());
+    }
+    if let Some(version) = version
+        && version > MIGRATION_TABLE_VERSION
+    {
+        return Err(LibsqlMigratorBaseError::UnsupportedTableVersion(version));
+    }
+
+    let tx = conn.transaction().await?;
+
+    tx.execute_batch(&table.sql(include_str!("./base_migration_table.sql")))
+        .await?;
+
+    let mut rows = tx
+        .query(
+            &table.sql("PRAGMA {schema}table_info({name});"),
+            libsql::params![],
+        )
+        .await?;
+
+    let mut existing_columns: Vec<String> = vec![];
+    while let Some(row) = rows.next().await? {
+        existing_columns.push(row.get::<String>(1)?);
+    }
+
+    let from = version.unwrap_or(1).max(1) as usize - 1;
+    for (column, column_type) in TABLE_UPGRADES[from..].iter().copied().flatten() {
+        if !existing_columns.iter().any(|existing| existing == column) {
+            tx.execute(
+                &format!(
+                    "ALTER TABLE {} ADD COLUMN {} {};",
+                    table.qualified_name(),
+                    column,
+                    column_type
+                ),
+                libsql::params![],
+            )
+            .await?;
+        }
+    }
+
+    tx.execute(
+        &table.sql("DELETE FROM {version_table};"),
+        libsql::params![],
+    )
+    .await?;
+    tx.execute(
+        &table.sql("INSERT INTO {version_table} (version) VALUES (?);"),
+        libsql::params![MIGRATION_TABLE_VERSION],
+    )
+    .await?;
+
+    tx.commit().await?;
 
-    conn.execute(sql_query, libsql::params![]).await?;
     Ok(())
 }
 
-pub(crate) fn validate_migration_folder(path: &Path) -> Result<(), LibsqlDirMigratorError> {
-    if !path.exists() {
-        return Err(LibsqlDirMigratorError::MigrationDirNotFound(
-            path.to_path_buf(),
-        ));
-    };
-    if path.is_dir() || (path.is_file() && path.extension().unwrap_or_default() == "sql") {
+/// Version recorded for the tracking table, or `None` if it was never versioned.
+async fn table_version(
+    conn: &Connection,
+    table: &MigrationTable,
+) -> Result<Option<i64>, LibsqlMigratorBaseError> {
+    let mut rows = conn
+        .query(
+            &table.sql("SELECT name FROM {schema}sqlite_master WHERE type = 'table' AND name = ?;"),
+            libsql::params![table.version_table_name()],
+        )
+        .await?;
+    if rows.next().await?.is_none() {
+        return Ok(None);
+    }
+
+    let mut rows = conn
+        .query(
+            &table.sql("SELECT MAX(version) FROM {version_table};"),
+            libsql::params![],
+        )
+        .await?;
+
+    match rows.next().await? {
+        Some(row) => Ok(row.get::<Option<i64>>(0)?),
+        None => Ok(None),
+    }
+}
+
+/// A migration listed by a [`MigrationSource`](crate::migrator::MigrationSource),
+/// ready to be applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    pub id: String,
+    /// SQL of the migration. Empty for migrations written in Rust.
+    pub script: String,
+    /// Script that reverts `script`, stored alongside the applied migration so it
+    /// can be rolled back later.
+    pub down_script: Option<String>,
+    code: Option<MigrationFn>,
+    loaded: bool,
+}
+
+impl Migration {
+    pub fn new(id: String, script: String, down_script: Option<String>) -> Self {
+        Self {
+            id,
+            script,
+            down_script,
+            code: None,
+            loaded: true,
+        }
+    }
+
+    /// An already applied migration, listed by
+    /// [`MigrationSource::pending_migrations`](crate::migrator::MigrationSource::pending_migrations)
+    /// without loading its script. The run skips it, or fails with
+    /// [`LibsqlMigratorBaseError::MigrationNotLoaded`] if it is no longer applied.
+    pub fn applied(id: impl Into<String>) -> Self {
+        Self {
+            loaded: false,
+            ..Self::new(id.into(), String::new(), None)
+        }
+    }
+
+    /// A migration written in Rust. `f` receives the connection the migration
+    /// runs on, inside the transaction wrapping it, and is tracked like a script.
+    ///
+    /// ```no_run
+    /// use libsql_migration::util::Migration;
+    ///
+    /// let backfill = Migration::from_fn("0003_backfill_slugs", |conn| {
+    ///     Box::pin(async move {
+    ///         conn.execute("UPDATE posts SET slug = lower(title);", ())
+    ///             .await?;
+    ///         Ok(())
+    ///     })
+    /// });
+    /// ```
+    pub fn from_fn<F>(id: impl Into<String>, f: F) -> Self
+    where
+        F: for<'c> Fn(&'c Connection) -> MigrationFuture<'c> + Send + Sync + 'static,
+    {
+        Self {
+            id: id.into(),
+            script: String::new(),
+            down_script: None,
+            code: Some(MigrationFn(Arc::new(f))),
+            loaded: true,
+        }
+    }
+
+    /// Whether the migration is written in Rust rather than SQL.
+    pub fn is_code(&self) -> bool {
+        self.code.is_some()
+    }
+
+    /// Checksum of the script, or `None` for migrations written in Rust, whose
+    /// changes cannot be detected.
+    pub(crate) fn checksum(&self) -> Option<String> {
+        if self.is_code() {
+            None
+        } else {
+            Some(checksum(&self.script))
+        }
+    }
+}
+
+/// Future returned by a migration written in Rust.
+pub type MigrationFuture<'c> =
+    Pin<Box<dyn Future<Output = Result<(), Box<dyn Error + Send + Sync>>> + Send + 'c>>;
+
+type MigrationFnBody = dyn for<'c> Fn(&'c Connection) -> MigrationFuture<'c> + Send + Sync;
+
+#[derive(Clone)]
+struct MigrationFn(Arc<MigrationFnBody>);
+
+impl fmt::Debug for MigrationFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MigrationFn")
+    }
+}
+
+impl PartialEq for MigrationFn {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for MigrationFn {}
+
+/// Orders migration ids the way migrations from different sources are
+/// interleaved: by their last path segment (the file name for files), then by
+/// the full id.
+pub(crate) fn compare_ids(a: &str, b: &str) -> Ordering {
+    fn last_segment(id: &str) -> &str {
+        Path::new(id)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(id)
+    }
+
+    last_segment(a).cmp(last_segment(b)).then_with(|| a.cmp(b))
+}
+
+/// How [`Migrator`](crate::migrator::Migrator) orders migrations, read from the
+/// id (the file name for files).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OrderingStrategy {
+    /// By file name, character by character, so `10_x.sql` runs before `9_x.sql`.
+    #[default]
+    Lexicographic,
+    /// By the number the file name starts with, so `9_x.sql` runs before
+    /// `10_x.sql`. Leading zeros are ignored.
+    NumericPrefix,
+    /// By a dotted version the file name starts with, optionally prefixed by `v`
+    /// or `V`: `v1.2_x.sql` runs before `v1.10_x.sql`. Missing components count as
+    /// zero, so `1.2` and `1.2.0` are the same version.
+    VersionPrefix,
+    /// By a timestamp of at least 8 digits the file name starts with, such as the
+    /// `YYYYMMDDHHMMSS` prefix of [`create_migration`](crate::dir::create_migration).
+    TimestampPrefix,
+}
+
+impl OrderingStrategy {
+    /// Version read from the last path segment of `id`, or `None` when it has
+    /// no prefix of the expected form. Always `Some` for `Lexicographic`, which
+    /// has no version.
+    fn version(&self, id: &str) -> Option<Vec<u128>> {
+        let name = Path::new(id)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(id);
+        let digits =
+            |text: &str| -> String { text.chars().take_while(|c| c.is_ascii_digit()).collect() };
+
+        match self {
+            OrderingStrategy::Lexicographic => Some(vec![]),
+            OrderingStrategy::NumericPrefix => Some(vec![digits(name).parse().ok()?]),
+            OrderingStrategy::TimestampPrefix => {
+                let prefix = digits(name);
+                if prefix.len() < 8 {
+                    return None;
+                }
+                Some(vec![prefix.parse().ok()?])
+            }
+            OrderingStrategy::VersionPrefix => {
+                let name = name.strip_prefix(['v', 'V']).unwrap_or(name);
+                let prefix: String = name
+                    .chars()
+                    .take_while(|c| c.is_ascii_digit() || *c == '.')
+                    .collect();
+
+                let mut components = prefix
+                    .trim_end_matches('.')
+                    .split('.')
+                    .map(|component| component.parse().ok())
+                    .collect::<Option<Vec<u128>>>()?;
+                while components.last() == Some(&0) {
+                    components.pop();
+                }
+                Some(components)
+            }
+        }
+    }
+
+    /// Compares two ids by version, then as [`Lexicographic`](Self::Lexicographic)
+    /// does. Ids without a version sort after the others.
+    pub(crate) fn compare(&self, a: &str, b: &str) -> Ordering {
+        match (self.version(a), self.version(b)) {
+            (Some(a_version), Some(b_version)) => a_version.cmp(&b_version),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+        .then_with(|| compare_ids(a, b))
+    }
+
+    /// Sorts `items` by the id returned by `id`.
+    ///
+    /// Fails with [`LibsqlMigratorBaseError::InvalidMigrationVersion`] for an id
+    /// without a version and with [`LibsqlMigratorBaseError::DuplicateMigrationVersion`]
+    /// when two ids have the same version.
+    pub(crate) fn sort<T>(
+        &self,
+        items: &mut [T],
+        id: impl Fn(&T) -> &str,
+    ) -> Result<(), LibsqlMigratorBaseError> {
+        if let Some(item) = items.iter().find(|item| self.version(id(item)).is_none()) {
+            return Err(LibsqlMigratorBaseError::InvalidMigrationVersion(
+                id(item).to_string(),
+            ));
+        }
+
+        items.sort_by(|a, b| self.compare(id(a), id(b)));
+
+        if *self == OrderingStrategy::Lexicographic {
+            return Ok(());
+        }
+
+        if let Some(pair) = items
+            .windows(2)
+            .find(|pair| self.version(id(&pair[0])) == self.version(id(&pair[1])))
+        {
+            return Err(LibsqlMigratorBaseError::DuplicateMigrationVersion(
+                id(&pair[0]).to_string(),
+                id(&pair[1]).to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Hex encoded SHA-256 of a migration script, stored when it is applied.
+pub(crate) fn checksum(sql_script: &str) -> String {
+    format!("{:x}", Sha256::digest(sql_script.as_bytes()))
+}
+
+/// Compares `migrations` against the checksums recorded when they were applied.
+///
+/// Fails with [`LibsqlMigratorBaseError::ChecksumMismatch`] listing every applied
+/// migration whose script has changed since. Migrations that are not applied yet,
+/// or were applied before checksums were recorded, are not checked. Only reads the
+/// tracking table.
+pub(crate) async fn verify_migrations(
+    conn: &Connection,
+    table: &MigrationTable,
+    migrations: &[Migration],
+) -> Result<(), LibsqlMigratorBaseError> {
+    let columns = table_columns(conn, table).await?;
+    if !columns.iter().any(|column| column == "checksum") {
+        return Ok(());
+    }
+
+    let mut stmt = conn
+        .prepare(&table.sql("SELECT checksum FROM {table} WHERE id = ? AND status = true;"))
+        .await?;
+
+    let mut modified: Vec<String> = vec![];
+
+    for migration in migrations {
+        let mut rows = stmt.query([migration.id.clone()]).await?;
+
+        if let Some(source_checksum) = migration.checksum()
+            && let Some(record) = rows.next().await?
+            && let libsql::Value::Text(applied_checksum) = record.get_value(0)?
+            && applied_checksum != source_checksum
+        {
+            modified.push(migration.id.clone());
+        }
+
+        stmt.reset();
+    }
+
+    if modified.is_empty() {
         Ok(())
     } else {
-        Err(LibsqlDirMigratorError::InvalidMigrationPath(
-            path.to_path_buf(),
-        ))
+        Err(LibsqlMigratorBaseError::ChecksumMismatch(modified))
+    }
+}
+
+/// State of a migration as reported by the `status` functions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationState {
+    /// Applied, and the source still matches the applied script.
+    Applied,
+    /// Present in the source but not applied yet.
+    Pending,
+    /// Its last attempt failed; see the `failed_migrations` functions for details.
+    Failed,
+    /// Applied, but no longer present in the source.
+    MissingFromSource,
+    /// Applied, but the script in the source changed since.
+    Modified,
+}
+
+/// Status of a single migration, as reported by the `status` functions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationStatus {
+    pub id: String,
+    pub state: MigrationState,
+    /// When the migration was applied, as stored in `libsql_migrations`.
+    pub applied_at: Option<String>,
+    /// Checksum recorded when the migration was applied, or the checksum of the
+    /// source script for migrations that were never applied. Always `None` for
+    /// migrations written in Rust.
+    pub checksum: Option<String>,
+}
+
+struct MigrationRecord {
+    id: String,
+    status: bool,
+    exec_time: Option<String>,
+    checksum: Option<String>,
+}
+
+fn optional_text(value: libsql::Value) -> Option<String> {
+    match value {
+        libsql::Value::Text(text) => Some(text),
+        _ => None,
     }
 }
 
+/// Builds the status of every migration in `migrations` (in source order),
+/// followed by applied migrations that are no longer in the source. Only reads the
+/// tracking table, and treats a missing table as nothing applied.
+pub(crate) async fn migration_status(
+    conn: &Connection,
+    table: &MigrationTable,
+    migrations: &[Migration],
+) -> Result<Vec<MigrationStatus>, LibsqlMigratorBaseError> {
+    let columns = table_columns(conn, table).await?;
+
+    let mut records: Vec<MigrationRecord> = vec![];
+    if !columns.is_empty() {
+        let mut rows = conn
+            .query(
+                &table.sql(&format!(
+                    "SELECT id, status, exec_time, {} FROM {{table}} ORDER BY exec_time, rowid;",
+                    column_or_null(&columns, "checksum")
+                )),
+                libsql::params![],
+            )
+            .await?;
+
+        while let Some(row) = rows.next().await? {
+            records.push(MigrationRecord {
+                id: row.get::<String>(0)?,
+                status: matches!(row.get_value(1)?, libsql::Value::Integer(1)),
+                exec_time: optional_text(row.get_value(2)?),
+                checksum: optional_text(row.get_value(3)?),
+            });
+        }
+    }
+
+    let mut statuses: Vec<MigrationStatus> = migrations
+        .iter()
+        .map(|migration| {
+            let source_checksum = migration.checksum();
+
+            match records.iter().find(|record| record.id == migration.id) {
+                None => MigrationStatus {
+                    id: migration.id.clone(),
+                    state: MigrationState::Pending,
+                    applied_at: None,
+                    checksum: source_checksum,
+                },
+                Some(record) => {
+                    let modified = match (&record.checksum, &source_checksum) {
+                        (Some(applied), Some(source)) => applied != source,
+                        _ => false,
+                    };
+                    let state = if !record.status {
+                        MigrationState::Failed
+                    } else if modified {
+                        MigrationState::Modified
+                    } else {
+                        MigrationState::Applied
+                    };
+
+                    MigrationStatus {
+                        id: record.id.clone(),
+                        state,
+                        applied_at: record.exec_time.clone(),
+                        checksum: if migration.is_code() {
+                            None
+                        } else {
+                            record.checksum.clone()
+                        },
+                    }
+                }
+            }
+        })
+        .collect();
+
+    for record in records {
+        if record.status && !migrations.iter().any(|migration| migration.id == record.id) {
+            statuses.push(MigrationStatus {
+                id: record.id,
+                state: MigrationState::MissingFromSource,
+                applied_at: record.exec_time,
+                checksum: record.checksum,
+            });
+        }
+    }
+
+    Ok(statuses)
+}
+
+/// Options accepted by the `*_with_options` functions.
+#[derive(Debug, Clone)]
+pub struct MigrateOptions {
+    /// Discover and validate migrations without executing any of them.
+    /// The tracking table is only read, never created or written to.
+    pub dry_run: bool,
+    /// Table used to track applied migrations.
+    pub table: MigrationTable,
+    /// How long to wait for the migration lock held by another process before
+    /// failing with [`LibsqlMigratorBaseError::LockTimeout`]. Defaults to 30
+    /// seconds.
+    pub lock_timeout: Duration,
+    /// How long the migration lock stays valid without being refreshed. A lock
+    /// left behind by a crashed process is taken over once it expires. It is
+    /// refreshed after each applied migration, so it must outlast the slowest one;
+    /// a run whose lock expired and was taken over fails with
+    /// [`LibsqlMigratorBaseError::LockLost`]. Defaults to 10 minutes.
+    ///
+    /// With the defaults, runs started within 10 minutes of a crash fail with
+    /// [`LibsqlMigratorBaseError::LockTimeout`] until the crashed run's lock
+    /// expires. Deployments restarting right after a crash should lower
+    /// `lock_ttl` or raise `lock_timeout` above it.
+    pub lock_ttl: Duration,
+    /// What to do with pending migrations that sort before an already applied one.
+    pub ordering: OrderingPolicy,
+    /// How migrations are ordered before they run, also used to find
+    /// out-of-order migrations.
+    pub strategy: OrderingStrategy,
+    /// How the `dir` functions identify files in subdirectories.
+    pub nested_dirs: NestedDirMode,
+}
+
+/// How a migrations directory with subdirectories is read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NestedDirMode {
+    /// The id of a file is its path relative to the directory, so `a/0001.sql`
+    /// and `b/0001.sql` are two migrations. Files are ordered by file name, then
+    /// by path.
+    #[default]
+    Namespaced,
+    /// The id of a file is its file name, so subdirectories only group files.
+    /// Two files with the same name fail with
+    /// [`LibsqlDirMigratorError::ConflictingMigrationFiles`](crate::errors::LibsqlDirMigratorError::ConflictingMigrationFiles).
+    Flat,
+}
+
+/// How a run handles pending migrations sorting before the latest applied one,
+/// typically added by a branch merged after newer migrations were applied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OrderingPolicy {
+    /// Fail with [`LibsqlMigratorBaseError::OutOfOrderMigrations`] before applying
+    /// anything.
+    Strict,
+    /// Apply them like any other pending migration.
+    #[default]
+    Allow,
+    /// Apply them and list them in [`MigrationReport::out_of_order`].
+    Warn,
+}
+
+impl Default for MigrateOptions {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            table: MigrationTable::default(),
+            lock_timeout: Duration::from_secs(30),
+            lock_ttl: Duration::from_secs(600),
+            ordering: OrderingPolicy::default(),
+            strategy: OrderingStrategy::default(),
+            nested_dirs: NestedDirMode::default(),
+        }
+    }
+}
+
+/// A migration that would be applied, as returned by the `plan` functions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedMigration {
+    pub id: String,
+    pub script: String,
+}
+
+/// Columns of the tracking table, or none if it does not exist.
+async fn table_columns(
+    conn: &Connection,
+    table: &MigrationTable,
+) -> Result<Vec<String>, LibsqlMigratorBaseError> {
+    let mut rows = conn
+        .query(
+            &table.sql("PRAGMA {schema}table_info({name});"),
+            libsql::params![],
+        )
+        .await?;
+
+    let mut columns: Vec<String> = vec![];
+    while let Some(row) = rows.next().await? {
+        columns.push(row.get::<String>(1)?);
+    }
+
+    Ok(columns)
+}
+
+/// `column`, or `NULL` when the tracking table predates it, for use in a `SELECT`.
+fn column_or_null<'a>(columns: &[String], column: &'a str) -> &'a str {
+    if columns.iter().any(|existing| existing == column) {
+        column
+    } else {
+        "NULL"
+    }
+}
+
+async fn migration_table_exists(
+    conn: &Connection,
+    table: &MigrationTable,
+) -> Result<bool, LibsqlMigratorBaseError> {
+    let mut rows = conn
+        .query(
+            &table.sql("SELECT name FROM {schema}sqlite_master WHERE type='table' AND name = ?;"),
+            libsql::params![table.name.clone()],
+        )
+        .await?;
+
+    Ok(rows.next().await?.is_some())
+}
+
+/// Ids of the successfully applied migrations. Only reads the tracking table, and
+/// treats a missing table as nothing applied.
+pub(crate) async fn applied_ids(
+    conn: &Connection,
+    table: &MigrationTable,
+) -> Result<Vec<String>, LibsqlMigratorBaseError> {
+    let mut applied: Vec<String> = vec![];
+
+    if migration_table_exists(conn, table).await? {
+        let mut rows = conn
+            .query(
+                &table.sql("SELECT id FROM {table} WHERE status = true;"),
+                libsql::params![],
+            )
+            .await?;
+
+        while let Some(row) = rows.next().await? {
+            applied.push(row.get::<String>(0)?);
+        }
+    }
+
+    Ok(applied)
+}
+
+/// Returns the migrations that are not applied yet, in the order they would run.
+/// Only reads `libsql_migrations`, and treats a missing table as nothing applied.
+pub(crate) async fn plan_migrations(
+    conn: &Connection,
+    table: &MigrationTable,
+    migrations: Vec<Migration>,
+) -> Result<Vec<PlannedMigration>, LibsqlMigratorBaseError> {
+    let applied = applied_ids(conn, table).await?;
+
+    Ok(migrations
+        .into_iter()
+        .filter(|migration| !applied.contains(&migration.id))
+        .map(|migration| PlannedMigration {
+            id: migration.id,
+            script: migration.script,
+        })
+        .collect())
+}
+
+/// Outcome of a migration run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// Ids of the migrations applied by this run, in the order they were applied.
+    /// For a dry run, the migrations that would have been applied.
+    pub applied: Vec<String>,
+    /// Ids of the migrations that were already applied and therefore skipped.
+    pub skipped: Vec<String>,
+    /// Time spent applying each migration in `applied`. Empty for a dry run.
+    pub durations: HashMap<String, Duration>,
+    /// Time spent on the whole run, including discovery and bookkeeping.
+    pub total_duration: Duration,
+    /// Whether this run was a dry run.
+    pub dry_run: bool,
+    /// Ids in `applied` that sort before a migration applied by an earlier run.
+    /// Only filled with [`OrderingPolicy::Warn`].
+    pub out_of_order: Vec<String>,
+}
+
+/// Checks the pending `migrations` against the applied ones as configured by
+/// [`MigrateOptions::ordering`], filling `report.out_of_order` when warning.
+async fn check_ordering(
+    conn: &Connection,
+    migrations: &[Migration],
+    options: &MigrateOptions,
+    report: &mut MigrationReport,
+) -> Result<(), LibsqlMigratorBaseError> {
+    if options.ordering == OrderingPolicy::Allow {
+        return Ok(());
+    }
+
+    let applied = applied_ids(conn, &options.table).await?;
+    let strategy = options.strategy;
+    let Some(latest) = applied.iter().max_by(|a, b| strategy.compare(a, b)) else {
+        return Ok(());
+    };
+
+    let out_of_order: Vec<String> = migrations
+        .iter()
+        .filter(|migration| {
+            !applied.contains(&migration.id)
+                && strategy.compare(&migration.id, latest) == Ordering::Less
+        })
+        .map(|migration| migration.id.clone())
+        .collect();
+
+    if out_of_order.is_empty() {
+        return Ok(());
+    }
+
+    match options.ordering {
+        OrderingPolicy::Strict => Err(LibsqlMigratorBaseError::OutOfOrderMigrations(out_of_order)),
+        _ => {
+            report.out_of_order = out_of_order;
+            Ok(())
+        }
+    }
+}
+
+/// Applies `migrations` in order, following `options`. `started` is when the run
+/// began, so the report includes the time spent discovering the migrations.
+pub(crate) async fn run_migrations(
+    conn: &Connection,
+    migrations: Vec<Migration>,
+    options: &MigrateOptions,
+    started: Instant,
+) -> Result<MigrationReport, LibsqlMigratorBaseError> {
+    let mut report = MigrationReport {
+        dry_run: options.dry_run,
+        ..Default::default()
+    };
+
+    if options.dry_run {
+        check_ordering(conn, &migrations, options, &mut report).await?;
+
+        let all_ids: Vec<String> = migrations.iter().map(|m| m.id.clone()).collect();
+        let planned = plan_migrations(conn, &options.table, migrations).await?;
+
+        report.skipped = all_ids
+            .into_iter()
+            .filter(|id| !planned.iter().any(|migration| migration.id == *id))
+            .collect();
+        report.applied = planned.into_iter().map(|migration| migration.id).collect();
+        report.total_duration = started.elapsed();
+
+        return Ok(report);
+    }
+
+    let lock = MigrationLock::acquire(conn, options).await?;
+    let result = apply_migrations(conn, migrations, options, &lock, &mut report).await;
+    lock.release_after(conn, &options.table, result).await?;
+
+    report.total_duration = started.elapsed();
+
+    Ok(report)
+}
+
+async fn apply_migrations(
+    conn: &Connection,
+    migrations: Vec<Migration>,
+    options: &MigrateOptions,
+    lock: &MigrationLock,
+    report: &mut MigrationReport,
+) -> Result<(), LibsqlMigratorBaseError> {
+    create_migration_table(conn, &options.table).await?;
+    check_ordering(conn, &migrations, options, report).await?;
+
+    for migration in migrations {
+        let migration_started = Instant::now();
+
+        match execute_migration(conn, &options.table, &migration).await? {
+            MigrationResult::Executed => {
+                report
+                    .durations
+                    .insert(migration.id.clone(), migration_started.elapsed());
+                report.applied.push(migration.id);
+                lock.refresh(conn, options).await?;
+            }
+            MigrationResult::AlreadyExecuted => report.skipped.push(migration.id),
+        }
+    }
+
+    Ok(())
+}
+
+/// Advisory lock serializing migration runs across processes.
+///
+/// Stored as a single row of the `<name>_lock` table kept next to the tracking
+/// table, with the owner and the time it expires at, in milliseconds since the
+/// Unix epoch.
+struct MigrationLock {
+    owner: String,
+}
+
+/// Delay between two attempts to take a lock held by another process.
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Current time in milliseconds since the Unix epoch, in SQL.
+const LOCK_NOW_SQL: &str = "CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER)";
+
+impl MigrationLock {
+    async fn acquire(
+        conn: &Connection,
+        options: &MigrateOptions,
+    ) -> Result<Self, LibsqlMigratorBaseError> {
+        let lock = Self {
+            owner: format!(
+                "{}-{}",
+                std::process::id(),
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_nanos()
+            ),
+        };
+
+        let started = Instant::now();
+        loop {
+            if lock.try_acquire(conn, options).await? {
+                return Ok(lock);
+            }
+
+            let elapsed = started.elapsed();
+            if elapsed >= options.lock_timeout {
+                let owner = lock_owner(conn, &options.table).await?;
+                return Err(LibsqlMigratorBaseError::LockTimeout(
+                    owner.unwrap_or_default(),
+                ));
+            }
+
+            tokio::time::sleep(LOCK_POLL_INTERVAL.min(options.lock_timeout - elapsed)).await;
+        }
+    }
+
+    /// Takes the lock if it is free or expired. A busy database counts as the
+    /// lock being held.
+    async fn try_acquire(
+        &self,
+        conn: &Connection,
+        options: &MigrateOptions,
+    ) -> Result<bool, LibsqlMigratorBaseError> {
+        let result = async {
+            conn.execute(
+                &options.table.sql(
+                    "CREATE TABLE IF NOT EXISTS {lock_table} (
+  id INTEGER PRIMARY KEY CHECK (id = 1),
+  owner TEXT NOT NULL,
+  acquired_at INTEGER NOT NULL,
+  expires_at INTEGER NOT NULL
+);",
+                ),
+                libsql::params![],
+            )
+            .await?;
+
+            conn.execute(
+                &options.table.sql(&format!(
+                    "INSERT INTO {{lock_table}} (id, owner, acquired_at, expires_at)
+            VALUES (1, ?, {now}, {now} + ?)
+            ON CONFLICT(id) DO UPDATE SET owner = excluded.owner,
+              acquired_at = excluded.acquired_at, expires_at = excluded.expires_at
+            WHERE expires_at <= excluded.acquired_at;",
+                    now = LOCK_NOW_SQL
+                )),
+                libsql::params![self.owner.as_str(), options.lock_ttl.as_millis() as i64],
+            )
+            .await
+        }
+        .await;
+
+        match result {
+            Ok(changed) => Ok(changed > 0),
+            Err(libsql::Error::SqliteFailure(code, _))
+                if code == libsql::ffi::SQLITE_BUSY || code == libsql::ffi::SQLITE_LOCKED =>
+            {
+                Ok(false)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Pushes the expiry of a held lock `lock_ttl` into the future.
+    ///
+    /// Fails with [`LibsqlMigratorBaseError::LockLost`] when the lock is no longer
+    /// held, typically because it expired and another process took it over.
+    async fn refresh(
+        &self,
+        conn: &Connection,
+        options: &MigrateOptions,
+    ) -> Result<(), LibsqlMigratorBaseError> {
+        let changed = conn
+            .execute(
+                &options.table.sql(&format!(
+                    "UPDATE {{lock_table}} SET expires_at = {} + ? WHERE id = 1 AND owner = ?;",
+                    LOCK_NOW_SQL
+                )),
+                libsql::params![options.lock_ttl.as_millis() as i64, self.owner.as_str()],
+            )
+            .await?;
+
+        if changed == 0 {
+            return Err(LibsqlMigratorBaseError::LockLost(
+                lock_owner(conn, &options.table).await?.unwrap_or_default(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn release(
+        self,
+        conn: &Connection,
+        table: &MigrationTable,
+    ) -> Result<(), LibsqlMigratorBaseError> {
+        conn.execute(
+            &table.sql("DELETE FROM {lock_table} WHERE id = 1 AND owner = ?;"),
+            libsql::params![self.owner],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Releases the lock once the run ended with `result`. A failed release is
+    /// only returned when `result` is `Ok`, so it never hides the run's error.
+    async fn release_after<T>(
+        self,
+        conn: &Connection,
+        table: &MigrationTable,
+        result: Result<T, LibsqlMigratorBaseError>,
+    ) -> Result<T, LibsqlMigratorBaseError> {
+        let released = self.release(conn, table).await;
+        let value = result?;
+        released?;
+
+        Ok(value)
+    }
+}
+
+async fn lock_owner(
+    conn: &Connection,
+    table: &MigrationTable,
+) -> Result<Option<String>, LibsqlMigratorBaseError> {
+    let mut rows = conn
+        .query(
+            &table.sql("SELECT owner FROM {lock_table} WHERE id = 1;"),
+            libsql::params![],
+        )
+        .await?;
+
+    match rows.next().await? {
+        Some(row) => Ok(Some(row.get::<String>(0)?)),
+        None => Ok(None),
+    }
+}
+
+/// A migration whose last attempt failed, as recorded in `libsql_migrations`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedMigration {
+    pub id: String,
+    /// Error reported by the database for the failing attempt.
+    pub error_message: Option<String>,
+    /// The statement of the script that failed, when the failure came from one.
+    pub failed_statement: Option<String>,
+    /// Number of attempts made at applying the migration.
+    pub attempts: i64,
+    pub last_attempt_at: Option<String>,
+}
+
+/// Lists every migration recorded as not applied, most recent attempt first.
+/// Only reads the tracking table, and treats a missing table as no failures.
+pub(crate) async fn query_failed_migrations(
+    conn: &Connection,
+    table: &MigrationTable,
+) -> Result<Vec<FailedMigration>, LibsqlMigratorBaseError> {
+    let columns = table_columns(conn, table).await?;
+    let mut failed = vec![];
+    if columns.is_empty() {
+        return Ok(failed);
+    }
+
+    let last_attempt_at = column_or_null(&columns, "last_attempt_at");
+    let mut rows = conn
+        .query(
+            &table.sql(&format!(
+                "SELECT id, {}, {}, {}, {} FROM {{table}}
+                WHERE status = false ORDER BY {} DESC, rowid DESC;",
+                column_or_null(&columns, "error_message"),
+                column_or_null(&columns, "failed_statement"),
+                column_or_null(&columns, "attempts"),
+                last_attempt_at,
+                last_attempt_at
+            )),
+            libsql::params![],
+        )
+        .await?;
+
+    while let Some(row) = rows.next().await? {
+        failed.push(FailedMigration {
+            id: row.get::<String>(0)?,
+            error_message: optional_text(row.get_value(1)?),
+            failed_statement: optional_text(row.get_value(2)?),
+            attempts: row.get::<Option<i64>>(3)?.unwrap_or_default(),
+            last_attempt_at: optional_text(row.get_value(4)?),
+        });
+    }
+
+    Ok(failed)
+}
+
 #[derive(Debug, PartialEq)]
 pub enum MigrationResult {
     Executed,
     AlreadyExecuted,
 }
 
+/// Directive that opts a migration out of the surrounding transaction.
+///
+/// Place it on the first line of a script that contains statements SQLite
+/// refuses to run inside a transaction (e.g. `VACUUM`). Such migrations are
+/// not rolled back on failure. The directive applies to down scripts as well.
+pub const NO_TRANSACTION_DIRECTIVE: &str = "-- no-transaction";
+
+fn is_transactional(sql_script: &str) -> bool {
+    sql_script
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .is_none_or(|line| line != NO_TRANSACTION_DIRECTIVE)
+}
+
 pub(crate) async fn execute_migration(
     conn: &Connection,
-    id: String,
-    sql_script: String,
+    table: &MigrationTable,
+    migration: &Migration,
 ) -> Result<MigrationResult, LibsqlMigratorBaseError> {
     let mut stmt = conn
-        .prepare("SELECT status FROM libsql_migrations WHERE id = ?;")
+        .prepare(&table.sql("SELECT status FROM {table} WHERE id = ?;"))
         .await?;
 
-    let mut rows = stmt.query([id.clone()]).await?;
+    let mut rows = stmt.query([migration.id.clone()]).await?;
 
     if let Some(record) = rows.next().await? {
         let status_value = record.get_value(0)?;
         if let libsql::Value::Integer(1) = status_value {
             return Ok(MigrationResult::AlreadyExecuted);
         }
+    }
+
+    if !migration.loaded {
+        return Err(LibsqlMigratorBaseError::MigrationNotLoaded(
+            migration.id.clone(),
+        ));
+    }
+
+    let result = if is_transactional(&migration.script) {
+        let tx = conn.transaction().await?;
+
+        match apply_migration(&tx, table, migration).await {
+            Ok(()) => tx.commit().await.map_err(LibsqlMigratorBaseError::from),
+            Err(e) => {
+                // Dropping the transaction rolls back as well, so a failed rollback
+                // must not hide the error that caused it.
+                tx.rollback().await.ok();
+                Err(e)
+            }
+        }
     } else {
         conn.execute(
-            "INSERT INTO libsql_migrations (id) VALUES (?) ON CONFLICT(id) DO NOTHING",
-            libsql::params![id.clone()],
+            &table.sql("INSERT INTO {table} (id) VALUES (?) ON CONFLICT(id) DO NOTHING"),
+            libsql::params![migration.id.clone()],
         )
         .await?;
+
+        apply_migration(conn, table, migration).await
+    };
+
+    if let Err(e) = result {
+        // Failing to record the failure must not replace the error that caused it.
+        record_failure(conn, table, &migration.id, &e).await.ok();
+        return Err(e);
     }
 
-    conn.execute(&sql_script, libsql::params!()).await?;
+    Ok(MigrationResult::Executed)
+}
+
+/// Records a failed attempt at applying migration `id`, keeping it at `status = false`.
+async fn record_failure(
+    conn: &Connection,
+    table: &MigrationTable,
+    id: &str,
+    error: &LibsqlMigratorBaseError,
+) -> Result<(), LibsqlMigratorBaseError> {
+    let (error_message, failed_statement) = match error {
+        LibsqlMigratorBaseError::StatementFailed {
+            statement, source, ..
+        } => (source.to_string(), Some(statement.clone())),
+        e => (e.to_string(), None),
+    };
+
+    conn.execute(
+        &table.sql(
+            "INSERT INTO {table} (id, status, error_message, failed_statement, attempts, last_attempt_at)
+            VALUES (?, false, ?, ?, 1, CURRENT_TIMESTAMP)
+            ON CONFLICT(id) DO UPDATE SET error_message = excluded.error_message,
+            failed_statement = excluded.failed_statement,
+            attempts = COALESCE(attempts, 0) + 1,
+            last_attempt_at = excluded.last_attempt_at",
+        ),
+        libsql::params![id, error_message, failed_statement],
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn apply_migration(
+    conn: &Connection,
+    table: &MigrationTable,
+    migration: &Migration,
+) -> Result<(), LibsqlMigratorBaseError> {
+    match &migration.code {
+        Some(code) => {
+            (code.0)(conn)
+                .await
+                .map_err(|source| LibsqlMigratorBaseError::CodeMigrationFailed {
+                    migration_id: migration.id.clone(),
+                    source,
+                })?
+        }
+        None => execute_script(conn, &migration.id, &migration.script).await?,
+    }
 
     conn.execute(
-        "UPDATE libsql_migrations SET status = true, exec_time = CURRENT_TIMESTAMP WHERE id = ?",
+        &table.sql(
+            "INSERT INTO {table} (id, status, exec_time, checksum, down_script, attempts, last_attempt_at)
+            VALUES (?, true, CURRENT_TIMESTAMP, ?, ?, 1, CURRENT_TIMESTAMP)
+            ON CONFLICT(id) DO UPDATE SET status = true, exec_time = CURRENT_TIMESTAMP,
+            checksum = excluded.checksum, down_script = excluded.down_script,
+            error_message = NULL, failed_statement = NULL,
+            attempts = COALESCE(attempts, 0) + 1,
+            last_attempt_at = excluded.last_attempt_at",
+        ),
+        libsql::params![
+            migration.id.clone(),
+            migration.checksum(),
+            migration.down_script.clone()
+        ],
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Reverts every migration applied after `target_id`, newest first, using the
+/// down scripts stored when they were applied. Reverts everything when
+/// `target_id` is `None`.
+///
+/// Nothing is reverted unless every affected migration has a down script.
+/// Returns the reverted ids in the order they were reverted.
+pub(crate) async fn rollback_migrations(
+    conn: &Connection,
+    options: &MigrateOptions,
+    target_id: Option<String>,
+) -> Result<Vec<String>, LibsqlMigratorBaseError> {
+    let lock = MigrationLock::acquire(conn, options).await?;
+    let result = revert_migrations(conn, &options.table, target_id).await;
+    lock.release_after(conn, &options.table, result).await
+}
+
+async fn revert_migrations(
+    conn: &Connection,
+    table: &MigrationTable,
+    target_id: Option<String>,
+) -> Result<Vec<String>, LibsqlMigratorBaseError> {
+    create_migration_table(conn, table).await?;
+
+    let mut rows = conn
+        .query(
+            &table.sql(
+                "SELECT id, down_script FROM {table} WHERE status = true ORDER BY exec_time, rowid;",
+            ),
+            libsql::params![],
+        )
+        .await?;
+
+    let mut applied: Vec<(String, Option<String>)> = vec![];
+    while let Some(row) = rows.next().await? {
+        applied.push((row.get::<String>(0)?, row.get::<Option<String>>(1)?));
+    }
+
+    let to_revert = match target_id {
+        Some(target_id) => {
+            let position = applied
+                .iter()
+                .position(|(id, _)| *id == target_id)
+                .ok_or(LibsqlMigratorBaseError::RollbackTargetNotFound(target_id))?;
+            applied.split_off(position + 1)
+        }
+        None => applied,
+    };
+
+    let missing_down: Vec<String> = to_revert
+        .iter()
+        .filter(|(_, down_script)| down_script.is_none())
+        .map(|(id, _)| id.clone())
+        .collect();
+
+    if !missing_down.is_empty() {
+        return Err(LibsqlMigratorBaseError::MissingDownMigration(missing_down));
+    }
+
+    let mut reverted = vec![];
+
+    for (id, down_script) in to_revert.into_iter().rev() {
+        let down_script = down_script.unwrap_or_default();
+
+        if is_transactional(&down_script) {
+            let tx = conn.transaction().await?;
+
+            if let Err(e) = revert_migration(&tx, table, &id, &down_script).await {
+                tx.rollback().await.ok();
+                return Err(e);
+            }
+
+            tx.commit().await?;
+        } else {
+            revert_migration(conn, table, &id, &down_script).await?;
+        }
+
+        reverted.push(id);
+    }
+
+    Ok(reverted)
+}
+
+async fn revert_migration(
+    conn: &Connection,
+    table: &MigrationTable,
+    id: &str,
+    down_script: &str,
+) -> Result<(), LibsqlMigratorBaseError> {
+    execute_script(conn, id, down_script).await?;
+
+    conn.execute(
+        &table.sql("DELETE FROM {table} WHERE id = ?;"),
         libsql::params![id],
     )
     .await?;
 
-    Ok(MigrationResult::Executed)
+    Ok(())
+}
+
+/// Runs every statement of `sql_script` in order, reporting the index of the
+/// statement that failed.
+async fn execute_script(
+    conn: &Connection,
+    id: &str,
+    sql_script: &str,
+) -> Result<(), LibsqlMigratorBaseError> {
+    for (statement_index, statement) in split_statements(sql_script).into_iter().enumerate() {
+        conn.execute_batch(statement).await.map_err(|source| {
+            LibsqlMigratorBaseError::StatementFailed {
+                migration_id: id.to_string(),
+                statement_index,
+                statement: statement.to_string(),
+                source,
+            }
+        })?;
+    }
+
+    Ok(())
 }
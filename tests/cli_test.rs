@@ -0,0 +1,178 @@
+#![cfg(feature = "cli")]
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+use tempfile::tempdir;
+
+mod common;
+
+#[cfg(test)]
+mod cli_tests {
+    use super::*;
+    use crate::common::TestServer;
+
+    fn cli(db_path: &Path, args: &[&str]) -> Result<Output, Box<dyn std::error::Error>> {
+        let output = Command::new(env!("CARGO_BIN_EXE_libsql-migration"))
+            .arg("--database")
+            .arg(db_path)
+            .args(args)
+            .env_remove("LIBSQL_DATABASE_URL")
+            .env_remove("LIBSQL_AUTH_TOKEN")
+            .output()?;
+
+        Ok(output)
+    }
+
+    fn stdout(output: &Output) -> String {
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+
+    #[test]
+    fn new_numbers_files() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db");
+        let migration_dir = temp_dir.path().join("migrations");
+        let dir = migration_dir.to_str().unwrap();
+
+        let output = cli(
+            &db_path,
+            &["new", "Create users", "--dir", dir, "--sequence"],
+        )?;
+        assert!(output.status.success());
+        assert!(migration_dir.join("0001_create_users.sql").exists());
+
+        let output = cli(
+            &db_path,
+            &[
+                "new",
+                "add email",
+                "--dir",
+                dir,
+                "--reversible",
+                "--sequence",
+            ],
+        )?;
+        assert!(output.status.success());
+        assert!(migration_dir.join("0002_add_email.up.sql").exists());
+        assert!(migration_dir.join("0002_add_email.down.sql").exists());
+
+        let output = cli(&db_path, &["new", "!!!", "--dir", dir])?;
+        assert!(!output.status.success());
+
+        let output = cli(&db_path, &["new", "add name", "--dir", dir])?;
+        assert!(output.status.success());
+        assert!(stdout(&output).contains("_add_name.sql"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn new_without_database() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let migration_dir = temp_dir.path().join("migrations");
+
+        let output = Command::new(env!("CARGO_BIN_EXE_libsql-migration"))
+            .args(["new", "create users", "--sequence", "--dir"])
+            .arg(&migration_dir)
+            .env_remove("LIBSQL_DATABASE_URL")
+            .output()?;
+        assert!(output.status.success());
+        assert!(migration_dir.join("0001_create_users.sql").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn up_status_verify_and_rollback() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db");
+        let migration_dir = temp_dir.path().join("migrations");
+        let dir = migration_dir.to_str().unwrap();
+
+        std::fs::create_dir_all(&migration_dir)?;
+        std::fs::write(
+            migration_dir.join("0001_users.up.sql"),
+            "CREATE TABLE users (id INTEGER PRIMARY KEY);",
+        )?;
+        std::fs::write(
+            migration_dir.join("0001_users.down.sql"),
+            "DROP TABLE users;",
+        )?;
+
+        let output = cli(&db_path, &["up", "--dir", dir, "--dry-run"])?;
+        assert!(output.status.success());
+        assert!(stdout(&output).contains("Would apply 0001_users.up.sql"));
+
+        let output = cli(&db_path, &["up", "--dir", dir])?;
+        assert!(output.status.success());
+        assert!(stdout(&output).contains("Applied 0001_users.up.sql"));
+
+        let output = cli(&db_path, &["status", "--dir", dir])?;
+        assert!(output.status.success());
+        assert!(stdout(&output).contains("Applied"));
+
+        let output = cli(&db_path, &["verify", "--dir", dir])?;
+        assert!(output.status.success());
+
+        std::fs::write(
+            migration_dir.join("0001_users.up.sql"),
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);",
+        )?;
+        let output = cli(&db_path, &["verify", "--dir", dir])?;
+        assert!(!output.status.success());
+
+        let output = cli(&db_path, &["rollback"])?;
+        assert!(!output.status.success());
+
+        let output = cli(&db_path, &["rollback", "--all"])?;
+        assert!(output.status.success());
+        assert!(stdout(&output).contains("Reverted 0001_users.up.sql"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn up_remote_skips_applied_scripts() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db");
+        let server = TestServer::start();
+        server
+            .route(
+                "/manifest.json",
+                vec![(200, r#"[{ "id": "0001", "url": "0001.sql" }]"#.to_string())],
+            )
+            .route(
+                "/0001.sql",
+                vec![(200, "CREATE TABLE users (id INTEGER);".to_string())],
+            );
+        let manifest = server.url("/manifest.json");
+
+        let output = cli(&db_path, &["up", "--remote", &manifest])?;
+        assert!(output.status.success());
+        assert!(stdout(&output).contains("Applied 0001"));
+
+        let output = cli(&db_path, &["up", "--remote", &manifest])?;
+        assert!(output.status.success());
+        assert!(stdout(&output).contains("1 already applied"));
+
+        assert_eq!(server.hits("/0001.sql"), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn requires_database() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+
+        let output = Command::new(env!("CARGO_BIN_EXE_libsql-migration"))
+            .args(["status", "--dir"])
+            .arg(temp_dir.path())
+            .env_remove("LIBSQL_DATABASE_URL")
+            .output()?;
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("no database given"));
+
+        Ok(())
+    }
+}
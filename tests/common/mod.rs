@@ -0,0 +1,146 @@
+//! Helpers shared by the integration tests. Each test crate uses a part of them.
+#![allow(dead_code)]
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Whether the table `name` exists in the main database of `conn`.
+pub async fn table_exists(
+    conn: &libsql::Connection,
+    name: &str,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut rows = conn
+        .query(
+            "SELECT name FROM sqlite_master WHERE type='table' AND name = ?;",
+            libsql::params![name],
+        )
+        .await?;
+
+    Ok(rows.next().await?.is_some())
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub path: String,
+    pub headers: HashMap<String, String>,
+}
+
+type Routes = HashMap<String, VecDeque<(u16, String, Duration)>>;
+
+/// Minimal HTTP server standing in for a migrations host.
+pub struct TestServer {
+    address: String,
+    routes: Arc<Mutex<Routes>>,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl TestServer {
+    /// Starts serving on a free local port. Unknown paths get a 404.
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        let routes: Arc<Mutex<Routes>> = Arc::default();
+        let requests: Arc<Mutex<Vec<RecordedRequest>>> = Arc::default();
+
+        let (served_routes, recorded) = (routes.clone(), requests.clone());
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let routes = served_routes.clone();
+                let recorded = recorded.clone();
+                thread::spawn(move || handle(stream, &routes, &recorded));
+            }
+        });
+
+        Self {
+            address,
+            routes,
+            requests,
+        }
+    }
+
+    /// Serves `responses` for `path` in turn, repeating the last one.
+    pub fn route(&self, path: &str, responses: Vec<(u16, String)>) -> &Self {
+        self.route_with_delays(
+            path,
+            responses
+                .into_iter()
+                .map(|(status, body)| (status, body, Duration::ZERO))
+                .collect(),
+        )
+    }
+
+    /// Same as [`route`](Self::route), waiting before sending each response.
+    pub fn route_with_delays(&self, path: &str, responses: Vec<(u16, String, Duration)>) -> &Self {
+        self.routes
+            .lock()
+            .unwrap()
+            .insert(path.to_string(), responses.into());
+        self
+    }
+
+    /// Number of requests received for `path`.
+    pub fn hits(&self, path: &str) -> usize {
+        self.requests()
+            .iter()
+            .filter(|request| request.path == path)
+            .count()
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.address, path)
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn handle(mut stream: TcpStream, routes: &Mutex<Routes>, recorded: &Mutex<Vec<RecordedRequest>>) {
+    let mut head = vec![];
+    let mut buffer = [0u8; 1024];
+    while !head.ends_with(b"\r\n\r\n") {
+        match stream.read(&mut buffer) {
+            Ok(0) | Err(_) => return,
+            Ok(read) => head.extend_from_slice(&buffer[..read]),
+        }
+    }
+
+    let head = String::from_utf8_lossy(&head).to_string();
+    let mut lines = head.lines();
+    let path = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .unwrap_or("/")
+        .to_string();
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+
+    recorded.lock().unwrap().push(RecordedRequest {
+        path: path.clone(),
+        headers,
+    });
+
+    let (status, body, delay) = {
+        let mut routes = routes.lock().unwrap();
+        match routes.get_mut(&path) {
+            Some(responses) if responses.len() > 1 => responses.pop_front().unwrap(),
+            Some(responses) => responses.front().cloned().unwrap(),
+            None => (404, "Not Found".to_string(), Duration::ZERO),
+        }
+    };
+    thread::sleep(delay);
+
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {} Status\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+}
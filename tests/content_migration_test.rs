@@ -1,3 +1,5 @@
+#![cfg(feature = "content")]
+
 use libsql_migration::content::migrate;
 use tempfile::tempdir;
 
@@ -98,7 +100,7 @@ mod migration_tests {
     }
 
     mod migration {
-        use libsql_migration::util::MigrationResult;
+        use libsql_migration::{content::migrate_with_options, util::MigrateOptions};
 
         use super::super::*;
         use crate::migration_tests::setup_test_db;
@@ -178,7 +180,8 @@ ADD Email TEXT;"
                     .to_string(),
             )
             .await?;
-            assert_eq!(res, MigrationResult::AlreadyExecuted);
+            assert!(res.applied.is_empty());
+            assert_eq!(res.skipped, vec!["001".to_string()]);
 
             let mut rows = conn
                 .query("PRAGMA table_info('test1');", libsql::params![])
@@ -241,5 +244,492 @@ ADD Email TEXT;"
 
             Ok(())
         }
+
+        #[tokio::test]
+        async fn migrate_with_options_reports() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+
+            let script = "CREATE TABLE test1 (id INTEGER PRIMARY KEY);".to_string();
+
+            let report = migrate_with_options(
+                &conn,
+                "001".to_string(),
+                script.clone(),
+                &MigrateOptions::default(),
+            )
+            .await?;
+            assert_eq!(report.applied, vec!["001".to_string()]);
+            assert!(report.durations.contains_key("001"));
+
+            let report =
+                migrate_with_options(&conn, "001".to_string(), script, &MigrateOptions::default())
+                    .await?;
+            assert!(report.applied.is_empty());
+            assert_eq!(report.skipped, vec!["001".to_string()]);
+
+            Ok(())
+        }
+    }
+
+    mod transaction {
+        use super::super::*;
+        use crate::migration_tests::setup_test_db;
+
+        #[tokio::test]
+        async fn failed_migration_is_rolled_back() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+
+            assert!(
+                migrate(
+                    &conn,
+                    "001".to_string(),
+                    "CREATE TABLE test1 (id INTEGER PRIMARY KEY);
+INSERT INTO missing_table (id) VALUES (1);"
+                        .to_string(),
+                )
+                .await
+                .is_err()
+            );
+
+            let mut rows = conn
+                .query(
+                    "SELECT name FROM sqlite_master WHERE type='table' AND name = 'test1';",
+                    libsql::params![],
+                )
+                .await?;
+
+            assert!(
+                rows.next().await?.is_none(),
+                "Failed migration should have been rolled back"
+            );
+
+            let mut rows = conn
+                .query(
+                    "SELECT status FROM libsql_migrations WHERE id = ?;",
+                    libsql::params!["001"],
+                )
+                .await?;
+
+            let row = rows.next().await?.expect("Failure was not recorded");
+            assert!(!row.get::<bool>(0)?, "Failed migration marked as applied");
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn no_transaction_directive() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+
+            assert!(
+                migrate(&conn, "001".to_string(), "VACUUM;".to_string())
+                    .await
+                    .is_err(),
+                "VACUUM should fail inside a transaction"
+            );
+
+            migrate(
+                &conn,
+                "002".to_string(),
+                "-- no-transaction
+VACUUM;"
+                    .to_string(),
+            )
+            .await?;
+
+            let mut rows = conn
+                .query(
+                    "SELECT status FROM libsql_migrations WHERE id = ?;",
+                    libsql::params!["002"],
+                )
+                .await?;
+
+            let row = rows.next().await?.expect("Migration record not found");
+            assert!(row.get::<bool>(0)?, "Migration should be marked as applied");
+
+            Ok(())
+        }
+    }
+
+    mod statements {
+        use libsql_migration::errors::{LibsqlContentMigratorError, LibsqlMigratorBaseError};
+
+        use super::super::*;
+        use crate::migration_tests::setup_test_db;
+
+        #[tokio::test]
+        async fn executes_every_statement() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+
+            migrate(
+                &conn,
+                "001".to_string(),
+                "CREATE TABLE test1 (
+  id INTEGER PRIMARY KEY autoincrement,
+  name TEXT
+);
+CREATE TABLE audit (message TEXT);
+CREATE INDEX idx_test1_name ON test1 (name);
+-- semicolons in comments; strings and trigger bodies must not split statements
+CREATE TRIGGER test1_insert AFTER INSERT ON test1
+BEGIN
+  INSERT INTO audit (message) VALUES ('inserted; ' || CASE WHEN NEW.name IS NULL THEN 'anonymous' ELSE NEW.name END);
+END;
+INSERT INTO test1 (name) VALUES ('first;');"
+                    .to_string(),
+            )
+            .await?;
+
+            let mut rows = conn
+                .query(
+                    "SELECT name FROM sqlite_master WHERE name IN ('test1', 'audit', 'idx_test1_name', 'test1_insert');",
+                    libsql::params![],
+                )
+                .await?;
+
+            let mut count = 0;
+            while rows.next().await?.is_some() {
+                count += 1;
+            }
+            assert_eq!(count, 4, "Not every statement was executed");
+
+            let mut rows = conn
+                .query("SELECT message FROM audit;", libsql::params![])
+                .await?;
+
+            let row = rows.next().await?.expect("Trigger did not fire");
+            assert_eq!(row.get::<String>(0)?, "inserted; first;");
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn reports_failing_statement_index() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+
+            let result = migrate(
+                &conn,
+                "001".to_string(),
+                "CREATE TABLE test1 (id INTEGER PRIMARY KEY);
+CREATE TABLE test2 (id INTEGER PRIMARY KEY);
+INSERT INTO missing_table (id) VALUES (1);"
+                    .to_string(),
+            )
+            .await;
+
+            match result {
+                Err(LibsqlContentMigratorError::BaseError(
+                    LibsqlMigratorBaseError::StatementFailed {
+                        migration_id,
+                        statement_index,
+                        ..
+                    },
+                )) => {
+                    assert_eq!(migration_id, "001");
+                    assert_eq!(statement_index, 2);
+                }
+                other => return Err(format!("Expected StatementFailed, got {:?}", other).into()),
+            }
+
+            let mut rows = conn
+                .query(
+                    "SELECT name FROM sqlite_master WHERE name IN ('test1', 'test2');",
+                    libsql::params![],
+                )
+                .await?;
+
+            assert!(
+                rows.next().await?.is_none(),
+                "Earlier statements should have been rolled back"
+            );
+
+            Ok(())
+        }
+    }
+
+    mod verify {
+        use libsql_migration::{
+            content::verify,
+            errors::{LibsqlContentMigratorError, LibsqlMigratorBaseError},
+        };
+
+        use super::super::*;
+        use crate::migration_tests::setup_test_db;
+
+        #[tokio::test]
+        async fn detects_changed_script() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+
+            let script = "CREATE TABLE test1 (id INTEGER PRIMARY KEY);".to_string();
+            migrate(&conn, "001".to_string(), script.clone()).await?;
+
+            verify(&conn, "001".to_string(), script).await?;
+
+            match verify(
+                &conn,
+                "001".to_string(),
+                "CREATE TABLE test1 (id INTEGER PRIMARY KEY, name TEXT);".to_string(),
+            )
+            .await
+            {
+                Err(LibsqlContentMigratorError::BaseError(
+                    LibsqlMigratorBaseError::ChecksumMismatch(ids),
+                )) => {
+                    assert_eq!(ids, vec!["001".to_string()]);
+                    Ok(())
+                }
+                other => Err(format!("Expected ChecksumMismatch, got {:?}", other).into()),
+            }
+        }
+    }
+
+    mod rollback {
+        use libsql_migration::content::{migrate_with_down, rollback};
+
+        use crate::migration_tests::setup_test_db;
+
+        #[tokio::test]
+        async fn reverts_all_migrations() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+
+            migrate_with_down(
+                &conn,
+                "001".to_string(),
+                "CREATE TABLE test1 (id INTEGER PRIMARY KEY);".to_string(),
+                "DROP TABLE test1;".to_string(),
+            )
+            .await?;
+            migrate_with_down(
+                &conn,
+                "002".to_string(),
+                "ALTER TABLE test1 ADD Email TEXT;".to_string(),
+                "ALTER TABLE test1 DROP COLUMN Email;".to_string(),
+            )
+            .await?;
+
+            let reverted = rollback(&conn, None).await?;
+            assert_eq!(reverted, vec!["002".to_string(), "001".to_string()]);
+
+            let mut rows = conn
+                .query(
+                    "SELECT name FROM sqlite_master WHERE type='table' AND name = 'test1';",
+                    libsql::params![],
+                )
+                .await?;
+            assert!(
+                rows.next().await?.is_none(),
+                "test1 should have been dropped"
+            );
+
+            let mut rows = conn
+                .query("SELECT id FROM libsql_migrations;", libsql::params![])
+                .await?;
+            assert!(
+                rows.next().await?.is_none(),
+                "Reverted rows were not removed"
+            );
+
+            Ok(())
+        }
+    }
+
+    mod status {
+        use libsql_migration::{
+            content::{ContentMigration, status},
+            util::MigrationState,
+        };
+
+        use super::super::*;
+        use crate::migration_tests::setup_test_db;
+
+        #[tokio::test]
+        async fn reports_applied_and_pending() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+
+            let script = "CREATE TABLE test1 (id INTEGER PRIMARY KEY);".to_string();
+            migrate(&conn, "001".to_string(), script.clone()).await?;
+
+            let statuses = status(
+                &conn,
+                vec![
+                    ContentMigration::new("001", script),
+                    ContentMigration::new("002", "ALTER TABLE test1 ADD Email TEXT;"),
+                ],
+            )
+            .await?;
+
+            assert_eq!(statuses.len(), 2);
+            assert_eq!(statuses[0].id, "001");
+            assert_eq!(statuses[0].state, MigrationState::Applied);
+            assert_eq!(statuses[1].id, "002");
+            assert_eq!(statuses[1].state, MigrationState::Pending);
+            assert!(statuses[1].applied_at.is_none());
+
+            Ok(())
+        }
+    }
+
+    mod failures {
+        use libsql_migration::content::failed_migrations;
+
+        use super::super::*;
+        use crate::migration_tests::setup_test_db;
+
+        #[tokio::test]
+        async fn records_failure_details() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+
+            let failing_script = "CREATE TABLE test1 (id INTEGER PRIMARY KEY);
+INSERT INTO missing_table (id) VALUES (1);"
+                .to_string();
+
+            for _ in 0..2 {
+                assert!(
+                    migrate(&conn, "001".to_string(), failing_script.clone())
+                        .await
+                        .is_err()
+                );
+            }
+
+            let failed = failed_migrations(&conn).await?;
+            assert_eq!(failed.len(), 1);
+            assert_eq!(failed[0].id, "001");
+            assert_eq!(failed[0].attempts, 2);
+            assert_eq!(
+                failed[0].failed_statement.as_deref(),
+                Some("INSERT INTO missing_table (id) VALUES (1);")
+            );
+            assert!(
+                failed[0]
+                    .error_message
+                    .as_deref()
+                    .is_some_and(|message| message.contains("missing_table"))
+            );
+            assert!(failed[0].last_attempt_at.is_some());
+
+            migrate(
+                &conn,
+                "001".to_string(),
+                "CREATE TABLE test1 (id INTEGER PRIMARY KEY);".to_string(),
+            )
+            .await?;
+
+            assert!(failed_migrations(&conn).await?.is_empty());
+
+            let mut rows = conn
+                .query(
+                    "SELECT attempts, error_message FROM libsql_migrations WHERE id = '001';",
+                    libsql::params![],
+                )
+                .await?;
+            let row = rows.next().await?.expect("Migration record not found");
+            assert_eq!(row.get::<i64>(0)?, 3);
+            assert!(row.get::<Option<String>>(1)?.is_none());
+
+            Ok(())
+        }
+    }
+    mod batch {
+        use libsql_migration::{
+            content::{ContentMigration, ContentSource, migrate_batch},
+            errors::LibsqlContentMigratorError,
+            migrator::Migrator,
+            util::MigrationState,
+        };
+
+        use crate::migration_tests::setup_test_db;
+
+        #[tokio::test]
+        async fn applies_pending_in_order() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+
+            // Ids are not sorted: the batch must run in the given order.
+            let mut migrations = vec![
+                ContentMigration::new("b_users", "CREATE TABLE users (id INTEGER PRIMARY KEY);")
+                    .with_down("DROP TABLE users;"),
+                ContentMigration::new("a_email", "ALTER TABLE users ADD email TEXT;")
+                    .with_down("ALTER TABLE users DROP COLUMN email;"),
+            ];
+
+            let report = migrate_batch(&conn, migrations.clone()).await?;
+            assert_eq!(
+                report.applied,
+                vec!["b_users".to_string(), "a_email".to_string()]
+            );
+
+            migrations.push(ContentMigration::new(
+                "c_name",
+                "ALTER TABLE users ADD name TEXT;",
+            ));
+            let report = migrate_batch(&conn, migrations).await?;
+            assert_eq!(
+                report.skipped,
+                vec!["b_users".to_string(), "a_email".to_string()]
+            );
+            assert_eq!(report.applied, vec!["c_name".to_string()]);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn runs_as_migrator_source() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+
+            let migrator = Migrator::new(ContentSource(vec![
+                ContentMigration::new("001", "CREATE TABLE users (id INTEGER PRIMARY KEY);"),
+                ContentMigration::new("002", "ALTER TABLE users ADD email TEXT;"),
+            ]));
+
+            assert_eq!(migrator.plan(&conn).await?.len(), 2);
+            assert_eq!(migrator.migrate(&conn).await?.applied, vec!["001", "002"]);
+            assert!(
+                migrator
+                    .status(&conn)
+                    .await?
+                    .iter()
+                    .all(|status| status.state == MigrationState::Applied)
+            );
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn validates_before_executing() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+
+            let invalid_batches = vec![
+                vec![
+                    ContentMigration::new("001", "CREATE TABLE test1 (id INTEGER);"),
+                    ContentMigration::new("", "CREATE TABLE test2 (id INTEGER);"),
+                ],
+                vec![
+                    ContentMigration::new("001", "CREATE TABLE test1 (id INTEGER);"),
+                    ContentMigration::new("002", ""),
+                ],
+                vec![
+                    ContentMigration::new("001", "CREATE TABLE test1 (id INTEGER);"),
+                    ContentMigration::new("001", "CREATE TABLE test2 (id INTEGER);"),
+                ],
+                vec![
+                    ContentMigration::new("001", "CREATE TABLE test1 (id INTEGER);").with_down(""),
+                ],
+            ];
+
+            for batch in invalid_batches {
+                match migrate_batch(&conn, batch).await {
+                    Err(LibsqlContentMigratorError::InvalidInput(_)) => {}
+                    other => return Err(format!("Expected InvalidInput, got {:?}", other).into()),
+                }
+            }
+
+            let mut rows = conn
+                .query(
+                    "SELECT name FROM sqlite_master WHERE type='table';",
+                    libsql::params![],
+                )
+                .await?;
+            assert!(rows.next().await?.is_none(), "Invalid batch executed");
+
+            Ok(())
+        }
     }
 }
@@ -1,7 +1,11 @@
+#![cfg(feature = "dir")]
+
 use libsql_migration::dir::migrate;
 use std::path::PathBuf;
 use tempfile::tempdir;
 
+mod common;
+
 #[cfg(test)]
 mod migration_tests {
     use std::fs;
@@ -277,5 +281,1491 @@ ADD Email TEXT;",
 
             Ok(())
         }
+
+        #[tokio::test]
+        async fn test_for_failing_migration_file() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+
+            std::fs::write(
+                migration_dir.join("0005_fail.sql"),
+                "INSERT INTO missing_table (id) VALUES (1);",
+            )?;
+
+            assert!(migrate(&conn, migration_dir.to_path_buf()).await.is_err());
+
+            let mut rows = conn
+                .query(
+                    "SELECT id FROM libsql_migrations WHERE status = true ORDER BY id;",
+                    libsql::params![],
+                )
+                .await?;
+
+            let mut applied: Vec<String> = vec![];
+            while let Some(row) = rows.next().await? {
+                applied.push(row.get::<String>(0)?);
+            }
+            assert_eq!(applied.len(), 5);
+
+            let failed = libsql_migration::dir::failed_migrations(&conn).await?;
+            assert_eq!(failed.len(), 1);
+            assert_eq!(failed[0].id, "0005_fail.sql");
+            assert_eq!(failed[0].attempts, 1);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_for_multi_statement_file() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+
+            std::fs::write(
+                migration_dir.join("0005_multi.sql"),
+                "CREATE TABLE test3 (id INTEGER PRIMARY KEY);
+CREATE TABLE test4 (id INTEGER PRIMARY KEY);
+CREATE INDEX idx_test1_email ON test1 (Email);",
+            )?;
+
+            migrate(&conn, migration_dir.to_path_buf()).await?;
+
+            let mut rows = conn
+                .query(
+                    "SELECT name FROM sqlite_master WHERE name IN ('test3', 'test4', 'idx_test1_email');",
+                    libsql::params![],
+                )
+                .await?;
+
+            let mut count = 0;
+            while rows.next().await?.is_some() {
+                count += 1;
+            }
+            assert_eq!(count, 3, "Not every statement was executed");
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_for_migration_report() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+
+            let report = migrate(&conn, migration_dir.clone()).await?;
+            assert!(!report.dry_run);
+            assert_eq!(
+                report.applied,
+                vec![
+                    "test/0001_test0.sql".to_string(),
+                    "0001_test1.sql".to_string(),
+                    "0002_est2.sql".to_string(),
+                    "0003_test3.sql".to_string(),
+                    "0004_test4.sql".to_string()
+                ]
+            );
+            assert!(report.skipped.is_empty());
+            assert!(
+                report
+                    .applied
+                    .iter()
+                    .all(|id| report.durations.contains_key(id))
+            );
+            assert!(
+                report.durations.values().sum::<std::time::Duration>() <= report.total_duration
+            );
+
+            std::fs::write(migration_dir.join("0005_test5.sql"), "SELECT 1;")?;
+
+            let report = migrate(&conn, migration_dir).await?;
+            assert_eq!(report.applied, vec!["0005_test5.sql".to_string()]);
+            assert_eq!(report.skipped.len(), 5);
+
+            Ok(())
+        }
+    }
+
+    mod verify {
+        use libsql_migration::{
+            dir::verify,
+            errors::{LibsqlDirMigratorError, LibsqlMigratorBaseError},
+        };
+
+        use super::super::*;
+        use crate::migration_tests::setup_test_db;
+
+        #[tokio::test]
+        async fn unchanged_files_pass() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+
+            migrate(&conn, migration_dir.clone()).await?;
+
+            // Pending files are not checked
+            std::fs::write(migration_dir.join("0005_test5.sql"), "SELECT 1;")?;
+
+            verify(&conn, migration_dir).await?;
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn modified_files_are_reported() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+
+            migrate(&conn, migration_dir.clone()).await?;
+
+            std::fs::write(
+                migration_dir.join("0004_test4.sql"),
+                "ALTER TABLE test2
+ADD Email TEXT;",
+            )?;
+
+            match verify(&conn, migration_dir).await {
+                Err(LibsqlDirMigratorError::BaseError(
+                    LibsqlMigratorBaseError::ChecksumMismatch(ids),
+                )) => {
+                    assert_eq!(ids, vec!["0004_test4.sql".to_string()]);
+                    Ok(())
+                }
+                other => Err(format!("Expected ChecksumMismatch, got {:?}", other).into()),
+            }
+        }
+
+        #[tokio::test]
+        async fn upgrades_tracking_table_without_checksum() -> Result<(), Box<dyn std::error::Error>>
+        {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+
+            conn.execute(
+                "CREATE TABLE libsql_migrations (
+  id TEXT PRIMARY KEY,
+  status BOOLEAN default false,
+  exec_time DATE
+);",
+                libsql::params![],
+            )
+            .await?;
+
+            migrate(&conn, migration_dir.clone()).await?;
+            verify(&conn, migration_dir).await?;
+
+            let mut rows = conn
+                .query(
+                    "SELECT COUNT(*) FROM libsql_migrations WHERE checksum IS NOT NULL;",
+                    libsql::params![],
+                )
+                .await?;
+
+            let row = rows.next().await?.expect("Count query returned no rows");
+            assert_eq!(row.get::<i64>(0)?, 5);
+
+            Ok(())
+        }
+    }
+
+    mod rollback {
+        use libsql_migration::{
+            dir::rollback,
+            errors::{LibsqlDirMigratorError, LibsqlMigratorBaseError},
+        };
+
+        use super::super::*;
+        use crate::{common::table_exists, migration_tests::setup_test_db};
+
+        fn write_paired_migrations(migration_dir: &std::path::Path) -> std::io::Result<()> {
+            std::fs::write(
+                migration_dir.join("0005_users.up.sql"),
+                "CREATE TABLE users (id INTEGER PRIMARY KEY);",
+            )?;
+            std::fs::write(
+                migration_dir.join("0005_users.down.sql"),
+                "DROP TABLE users;",
+            )?;
+            std::fs::write(
+                migration_dir.join("0006_posts.up.sql"),
+                "CREATE TABLE posts (id INTEGER PRIMARY KEY);",
+            )?;
+            std::fs::write(
+                migration_dir.join("0006_posts.down.sql"),
+                "DROP TABLE posts;",
+            )
+        }
+
+        #[tokio::test]
+        async fn reverts_to_target() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+            write_paired_migrations(&migration_dir)?;
+
+            migrate(&conn, migration_dir.clone()).await?;
+            assert!(table_exists(&conn, "users").await?);
+            assert!(table_exists(&conn, "posts").await?);
+
+            let reverted = rollback(&conn, Some("0005_users.up.sql".to_string())).await?;
+            assert_eq!(reverted, vec!["0006_posts.up.sql".to_string()]);
+            assert!(table_exists(&conn, "users").await?);
+            assert!(!table_exists(&conn, "posts").await?);
+
+            let reverted = rollback(&conn, Some("0004_test4.sql".to_string())).await?;
+            assert_eq!(reverted, vec!["0005_users.up.sql".to_string()]);
+            assert!(!table_exists(&conn, "users").await?);
+
+            let mut rows = conn
+                .query(
+                    "SELECT id FROM libsql_migrations WHERE id LIKE '%.up.sql';",
+                    libsql::params![],
+                )
+                .await?;
+            assert!(
+                rows.next().await?.is_none(),
+                "Reverted rows were not removed"
+            );
+
+            // Reverted migrations are applied again on the next run
+            migrate(&conn, migration_dir).await?;
+            assert!(table_exists(&conn, "posts").await?);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn refuses_without_down_script() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+            write_paired_migrations(&migration_dir)?;
+
+            migrate(&conn, migration_dir).await?;
+
+            match rollback(&conn, Some("0002_est2.sql".to_string())).await {
+                Err(LibsqlDirMigratorError::BaseError(
+                    LibsqlMigratorBaseError::MissingDownMigration(ids),
+                )) => {
+                    assert_eq!(
+                        ids,
+                        vec!["0003_test3.sql".to_string(), "0004_test4.sql".to_string()]
+                    );
+                }
+                other => {
+                    return Err(format!("Expected MissingDownMigration, got {:?}", other).into());
+                }
+            }
+
+            assert!(
+                table_exists(&conn, "posts").await?,
+                "Nothing should be reverted when a down script is missing"
+            );
+
+            assert!(matches!(
+                rollback(&conn, Some("9999_missing.sql".to_string())).await,
+                Err(LibsqlDirMigratorError::BaseError(
+                    LibsqlMigratorBaseError::RollbackTargetNotFound(_)
+                ))
+            ));
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn down_file_without_up_file() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+
+            std::fs::write(
+                migration_dir.join("0005_users.down.sql"),
+                "DROP TABLE users;",
+            )?;
+
+            assert!(matches!(
+                migrate(&conn, migration_dir).await,
+                Err(LibsqlDirMigratorError::ErrorWhileGettingSQLFiles(_))
+            ));
+
+            Ok(())
+        }
+    }
+
+    mod status {
+        use libsql_migration::{
+            dir::{failed_migrations, status, verify},
+            util::{MigrationState, MigrationStatus},
+        };
+
+        use super::super::*;
+        use crate::migration_tests::setup_test_db;
+
+        fn state_of(statuses: &[MigrationStatus], id: &str) -> Option<MigrationState> {
+            statuses
+                .iter()
+                .find(|status| status.id == id)
+                .map(|status| status.state.clone())
+        }
+
+        #[tokio::test]
+        async fn reports_every_state() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+
+            let statuses = status(&conn, migration_dir.clone()).await?;
+            assert_eq!(statuses.len(), 5);
+            assert!(
+                statuses
+                    .iter()
+                    .all(|status| status.state == MigrationState::Pending)
+            );
+
+            migrate(&conn, migration_dir.clone()).await?;
+
+            std::fs::write(migration_dir.join("0003_test3.sql"), "SELECT 1;")?;
+            std::fs::remove_file(migration_dir.join("0002_est2.sql"))?;
+            std::fs::write(migration_dir.join("0005_test5.sql"), "SELECT 1;")?;
+            std::fs::write(migration_dir.join("0006_test6.sql"), "SELECT 1;")?;
+            conn.execute(
+                "INSERT INTO libsql_migrations (id, status) VALUES ('0006_test6.sql', false);",
+                libsql::params![],
+            )
+            .await?;
+
+            let statuses = status(&conn, migration_dir).await?;
+
+            assert_eq!(
+                state_of(&statuses, "0001_test1.sql"),
+                Some(MigrationState::Applied)
+            );
+            assert_eq!(
+                state_of(&statuses, "0002_est2.sql"),
+                Some(MigrationState::MissingFromSource)
+            );
+            assert_eq!(
+                state_of(&statuses, "0003_test3.sql"),
+                Some(MigrationState::Modified)
+            );
+            assert_eq!(
+                state_of(&statuses, "0005_test5.sql"),
+                Some(MigrationState::Pending)
+            );
+            assert_eq!(
+                state_of(&statuses, "0006_test6.sql"),
+                Some(MigrationState::Failed)
+            );
+
+            let applied = statuses
+                .iter()
+                .find(|status| status.id == "0001_test1.sql")
+                .unwrap();
+            assert!(applied.applied_at.is_some());
+            assert!(applied.checksum.is_some());
+
+            // Nothing was applied while reporting
+            let mut rows = conn
+                .query(
+                    "SELECT id FROM libsql_migrations WHERE id = '0005_test5.sql';",
+                    libsql::params![],
+                )
+                .await?;
+            assert!(rows.next().await?.is_none());
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn reporting_creates_no_tables() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+
+            status(&conn, migration_dir.clone()).await?;
+            verify(&conn, migration_dir).await?;
+            assert!(failed_migrations(&conn).await?.is_empty());
+
+            let mut rows = conn
+                .query(
+                    "SELECT COUNT(*) FROM sqlite_master WHERE name LIKE 'libsql_migrations%';",
+                    libsql::params![],
+                )
+                .await?;
+            assert_eq!(rows.next().await?.unwrap().get::<i64>(0)?, 0);
+
+            Ok(())
+        }
+    }
+
+    mod plan {
+        use libsql_migration::{
+            dir::{migrate_with_options, plan},
+            util::MigrateOptions,
+        };
+
+        use super::super::*;
+        use crate::migration_tests::setup_test_db;
+
+        #[tokio::test]
+        async fn lists_pending_in_order() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+
+            let planned = plan(&conn, migration_dir.clone()).await?;
+            let ids: Vec<&str> = planned
+                .iter()
+                .map(|migration| migration.id.as_str())
+                .collect();
+            assert_eq!(
+                ids,
+                vec![
+                    "test/0001_test0.sql",
+                    "0001_test1.sql",
+                    "0002_est2.sql",
+                    "0003_test3.sql",
+                    "0004_test4.sql"
+                ]
+            );
+            assert!(planned[0].script.contains("CREATE TABLE test2"));
+
+            let mut rows = conn
+                .query(
+                    "SELECT name FROM sqlite_master WHERE type='table' AND name = 'libsql_migrations';",
+                    libsql::params![],
+                )
+                .await?;
+            assert!(
+                rows.next().await?.is_none(),
+                "Planning should not create the migrations table"
+            );
+
+            migrate(&conn, migration_dir.clone()).await?;
+            std::fs::write(migration_dir.join("0005_test5.sql"), "SELECT 1;")?;
+
+            let planned = plan(&conn, migration_dir).await?;
+            assert_eq!(planned.len(), 1);
+            assert_eq!(planned[0].id, "0005_test5.sql");
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn dry_run_executes_nothing() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+
+            let options = MigrateOptions {
+                dry_run: true,
+                ..Default::default()
+            };
+
+            let report = migrate_with_options(&conn, migration_dir.clone(), &options).await?;
+            assert!(report.dry_run);
+            assert_eq!(report.applied.len(), 5);
+            assert!(report.durations.is_empty());
+
+            let mut rows = conn
+                .query(
+                    "SELECT name FROM sqlite_master WHERE type='table';",
+                    libsql::params![],
+                )
+                .await?;
+            assert!(rows.next().await?.is_none(), "Dry run created tables");
+
+            migrate(&conn, migration_dir.clone()).await?;
+            let report = migrate_with_options(&conn, migration_dir, &options).await?;
+            assert!(report.applied.is_empty());
+            assert_eq!(report.skipped.len(), 5);
+
+            Ok(())
+        }
+    }
+    mod table {
+        use libsql_migration::{
+            dir::{migrate_with_options, rollback_with_options, status_with_options},
+            util::{MigrateOptions, MigrationState, MigrationTable},
+        };
+
+        use super::super::*;
+        use crate::{common::table_exists, migration_tests::setup_test_db};
+
+        #[tokio::test]
+        async fn uses_custom_table_name() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+
+            let options = MigrateOptions {
+                table: MigrationTable::new("core_migrations"),
+                ..Default::default()
+            };
+
+            let report = migrate_with_options(&conn, migration_dir.clone(), &options).await?;
+            assert_eq!(report.applied.len(), 5);
+
+            assert!(table_exists(&conn, "core_migrations").await?);
+            assert!(!table_exists(&conn, "libsql_migrations").await?);
+
+            let statuses = status_with_options(&conn, migration_dir, &options).await?;
+            assert!(
+                statuses
+                    .iter()
+                    .all(|status| status.state == MigrationState::Applied)
+            );
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn independent_sets_do_not_collide() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, temp_dir, migration_dir) = setup_test_db().await?;
+
+            let plugin_dir = temp_dir.path().join("plugin");
+            std::fs::create_dir_all(&plugin_dir)?;
+            std::fs::write(
+                plugin_dir.join("0001_test1.up.sql"),
+                "CREATE TABLE plugin (id INTEGER PRIMARY KEY);",
+            )?;
+            std::fs::write(plugin_dir.join("0001_test1.down.sql"), "DROP TABLE plugin;")?;
+
+            let plugin_options = MigrateOptions {
+                table: MigrationTable::new("plugin_migrations"),
+                ..Default::default()
+            };
+
+            migrate(&conn, migration_dir).await?;
+            let report = migrate_with_options(&conn, plugin_dir, &plugin_options).await?;
+            assert_eq!(report.applied, vec!["0001_test1.up.sql".to_string()]);
+
+            let reverted = rollback_with_options(&conn, None, &plugin_options).await?;
+            assert_eq!(reverted, vec!["0001_test1.up.sql".to_string()]);
+            assert!(!table_exists(&conn, "plugin").await?);
+            assert!(table_exists(&conn, "test1").await?);
+
+            let mut rows = conn
+                .query("SELECT COUNT(*) FROM libsql_migrations;", libsql::params![])
+                .await?;
+            let count: i64 = rows.next().await?.unwrap().get(0)?;
+            assert_eq!(count, 5);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn uses_attached_schema() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, temp_dir, migration_dir) = setup_test_db().await?;
+
+            let aux_path = temp_dir.path().join("aux.db");
+            conn.execute(
+                &format!("ATTACH DATABASE '{}' AS aux;", aux_path.display()),
+                libsql::params![],
+            )
+            .await?;
+
+            let options = MigrateOptions {
+                table: MigrationTable::new("m").with_schema("aux"),
+                ..Default::default()
+            };
+
+            let report = migrate_with_options(&conn, migration_dir.clone(), &options).await?;
+            assert_eq!(report.applied.len(), 5);
+
+            let mut rows = conn
+                .query("SELECT COUNT(*) FROM aux.m;", libsql::params![])
+                .await?;
+            let count: i64 = rows.next().await?.unwrap().get(0)?;
+            assert_eq!(count, 5);
+            assert!(!table_exists(&conn, "m").await?);
+
+            let report = migrate_with_options(&conn, migration_dir, &options).await?;
+            assert_eq!(report.skipped.len(), 5);
+
+            Ok(())
+        }
+    }
+    mod upgrade {
+        use libsql_migration::{
+            dir::{failed_migrations, status, verify},
+            errors::{LibsqlDirMigratorError, LibsqlMigratorBaseError},
+            util::{MIGRATION_TABLE_VERSION, MigrationState},
+        };
+
+        use super::super::*;
+        use crate::migration_tests::setup_test_db;
+
+        /// Creates the tracking table as released in 0.2.x, with one applied row.
+        async fn create_legacy_table(
+            conn: &libsql::Connection,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS libsql_migrations (
+  id TEXT PRIMARY KEY,
+  status BOOLEAN default false,
+  exec_time DATE
+);
+CREATE TABLE test2 (id INTEGER PRIMARY KEY autoincrement);
+INSERT INTO libsql_migrations (id, status, exec_time)
+VALUES ('test/0001_test0.sql', true, CURRENT_TIMESTAMP);",
+            )
+            .await?;
+            Ok(())
+        }
+
+        async fn stored_version(
+            conn: &libsql::Connection,
+        ) -> Result<i64, Box<dyn std::error::Error>> {
+            let mut rows = conn
+                .query(
+                    "SELECT version FROM libsql_migrations_version;",
+                    libsql::params![],
+                )
+                .await?;
+            Ok(rows.next().await?.expect("No version recorded").get(0)?)
+        }
+
+        #[tokio::test]
+        async fn upgrades_legacy_table() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+            create_legacy_table(&conn).await?;
+
+            let report = migrate(&conn, migration_dir.clone()).await?;
+            assert_eq!(report.skipped, vec!["test/0001_test0.sql".to_string()]);
+            assert_eq!(report.applied.len(), 4);
+
+            assert_eq!(stored_version(&conn).await?, MIGRATION_TABLE_VERSION);
+
+            let mut rows = conn
+                .query("PRAGMA table_info(libsql_migrations);", libsql::params![])
+                .await?;
+            let mut columns: Vec<String> = vec![];
+            while let Some(row) = rows.next().await? {
+                columns.push(row.get(1)?);
+            }
+            for column in [
+                "checksum",
+                "down_script",
+                "error_message",
+                "failed_statement",
+                "attempts",
+                "last_attempt_at",
+            ] {
+                assert!(columns.iter().any(|c| c == column), "Missing {}", column);
+            }
+
+            let statuses = status(&conn, migration_dir).await?;
+            assert!(
+                statuses
+                    .iter()
+                    .all(|status| status.state == MigrationState::Applied)
+            );
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn upgrade_is_idempotent() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+            create_legacy_table(&conn).await?;
+
+            migrate(&conn, migration_dir.clone()).await?;
+            migrate(&conn, migration_dir).await?;
+
+            let mut rows = conn
+                .query(
+                    "SELECT COUNT(*) FROM libsql_migrations_version;",
+                    libsql::params![],
+                )
+                .await?;
+            assert_eq!(rows.next().await?.unwrap().get::<i64>(0)?, 1);
+            assert_eq!(stored_version(&conn).await?, MIGRATION_TABLE_VERSION);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn reads_legacy_table_without_upgrading() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+            create_legacy_table(&conn).await?;
+
+            let statuses = status(&conn, migration_dir.clone()).await?;
+            assert_eq!(statuses.len(), 5);
+            let legacy = statuses
+                .iter()
+                .find(|status| status.id == "test/0001_test0.sql")
+                .unwrap();
+            assert_eq!(legacy.state, MigrationState::Applied);
+            assert_eq!(legacy.checksum, None);
+            assert_eq!(
+                statuses
+                    .iter()
+                    .filter(|status| status.state == MigrationState::Pending)
+                    .count(),
+                4
+            );
+
+            verify(&conn, migration_dir).await?;
+            assert!(failed_migrations(&conn).await?.is_empty());
+
+            let mut rows = conn
+                .query(
+                    "SELECT COUNT(*) FROM sqlite_master WHERE name LIKE 'libsql_migrations%';",
+                    libsql::params![],
+                )
+                .await?;
+            assert_eq!(rows.next().await?.unwrap().get::<i64>(0)?, 1);
+
+            let mut rows = conn
+                .query(
+                    "SELECT COUNT(*) FROM pragma_table_info('libsql_migrations');",
+                    libsql::params![],
+                )
+                .await?;
+            assert_eq!(rows.next().await?.unwrap().get::<i64>(0)?, 3);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn rejects_newer_table_version() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+
+            migrate(&conn, migration_dir.clone()).await?;
+            conn.execute(
+                "UPDATE libsql_migrations_version SET version = version + 1;",
+                libsql::params![],
+            )
+            .await?;
+
+            match migrate(&conn, migration_dir).await {
+                Err(LibsqlDirMigratorError::BaseError(
+                    LibsqlMigratorBaseError::UnsupportedTableVersion(version),
+                )) => {
+                    assert_eq!(version, MIGRATION_TABLE_VERSION + 1);
+                    Ok(())
+                }
+                other => Err(format!("Expected UnsupportedTableVersion, got {:?}", other).into()),
+            }
+        }
+    }
+    mod lock {
+        use std::time::Duration;
+
+        use libsql_migration::{
+            dir::migrate_with_options,
+            errors::{LibsqlDirMigratorError, LibsqlMigratorBaseError},
+            util::MigrateOptions,
+        };
+
+        use super::super::*;
+        use crate::migration_tests::setup_test_db;
+
+        async fn hold_lock(
+            conn: &libsql::Connection,
+            expires_in_ms: i64,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            conn.execute_batch(&format!(
+                "CREATE TABLE libsql_migrations_lock (
+  id INTEGER PRIMARY KEY CHECK (id = 1),
+  owner TEXT NOT NULL,
+  acquired_at INTEGER NOT NULL,
+  expires_at INTEGER NOT NULL
+);
+INSERT INTO libsql_migrations_lock VALUES
+  (1, 'other-process', CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER),
+   CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER) + {});",
+                expires_in_ms
+            ))
+            .await?;
+            Ok(())
+        }
+
+        async fn lock_rows(conn: &libsql::Connection) -> Result<i64, Box<dyn std::error::Error>> {
+            let mut rows = conn
+                .query(
+                    "SELECT COUNT(*) FROM libsql_migrations_lock;",
+                    libsql::params![],
+                )
+                .await?;
+            Ok(rows.next().await?.unwrap().get(0)?)
+        }
+
+        #[tokio::test]
+        async fn releases_lock_after_run() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+
+            migrate(&conn, migration_dir.clone()).await?;
+            assert_eq!(lock_rows(&conn).await?, 0);
+
+            std::fs::write(migration_dir.join("0005_test5.sql"), "INVALID SQL;")?;
+            assert!(migrate(&conn, migration_dir).await.is_err());
+            assert_eq!(lock_rows(&conn).await?, 0);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn failed_release_keeps_migration_error() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+            std::fs::write(
+                migration_dir.join("0005_test5.sql"),
+                "-- no-transaction\nDROP TABLE libsql_migrations_lock;\nINSERT INTO missing VALUES (1);",
+            )?;
+
+            match migrate(&conn, migration_dir).await {
+                Err(LibsqlDirMigratorError::BaseError(
+                    LibsqlMigratorBaseError::StatementFailed {
+                        statement_index, ..
+                    },
+                )) => assert_eq!(statement_index, 1),
+                other => return Err(format!("Expected StatementFailed, got {:?}", other).into()),
+            }
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn times_out_when_lock_is_held() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+            hold_lock(&conn, 600_000).await?;
+
+            let options = MigrateOptions {
+                lock_timeout: Duration::from_millis(200),
+                ..Default::default()
+            };
+
+            match migrate_with_options(&conn, migration_dir, &options).await {
+                Err(LibsqlDirMigratorError::BaseError(LibsqlMigratorBaseError::LockTimeout(
+                    owner,
+                ))) => assert_eq!(owner, "other-process"),
+                other => return Err(format!("Expected LockTimeout, got {:?}", other).into()),
+            }
+
+            let mut rows = conn
+                .query(
+                    "SELECT name FROM sqlite_master WHERE name = 'test1';",
+                    libsql::params![],
+                )
+                .await?;
+            assert!(
+                rows.next().await?.is_none(),
+                "Migrations ran without the lock"
+            );
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn takes_over_expired_lock() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+            hold_lock(&conn, -1).await?;
+
+            let options = MigrateOptions {
+                lock_timeout: Duration::ZERO,
+                ..Default::default()
+            };
+
+            let report = migrate_with_options(&conn, migration_dir, &options).await?;
+            assert_eq!(report.applied.len(), 5);
+            assert_eq!(lock_rows(&conn).await?, 0);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn keeps_sub_second_ttl() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, temp_dir, _) = setup_test_db().await?;
+            let migration_dir = temp_dir.path().join("ttl");
+            std::fs::create_dir_all(&migration_dir)?;
+            std::fs::write(
+                migration_dir.join("0001_ttl.sql"),
+                "CREATE TABLE ttl AS SELECT expires_at - acquired_at AS ttl FROM libsql_migrations_lock;",
+            )?;
+
+            let options = MigrateOptions {
+                lock_ttl: Duration::from_millis(500),
+                ..Default::default()
+            };
+            migrate_with_options(&conn, migration_dir, &options).await?;
+
+            let mut rows = conn
+                .query("SELECT ttl FROM ttl;", libsql::params![])
+                .await?;
+            assert_eq!(rows.next().await?.unwrap().get::<i64>(0)?, 500);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn reports_lost_lock() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+            std::fs::write(
+                migration_dir.join("0005_test5.sql"),
+                "UPDATE libsql_migrations_lock SET owner = 'other-process';",
+            )?;
+            std::fs::write(migration_dir.join("0006_test6.sql"), "SELECT 1;")?;
+
+            match migrate(&conn, migration_dir).await {
+                Err(LibsqlDirMigratorError::BaseError(LibsqlMigratorBaseError::LockLost(
+                    owner,
+                ))) => assert_eq!(owner, "other-process"),
+                other => return Err(format!("Expected LockLost, got {:?}", other).into()),
+            }
+
+            let mut rows = conn
+                .query(
+                    "SELECT id FROM libsql_migrations WHERE id = '0006_test6.sql';",
+                    libsql::params![],
+                )
+                .await?;
+            assert!(rows.next().await?.is_none(), "Ran without the lock");
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn concurrent_runs_apply_each_migration_once()
+        -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, temp_dir, migration_dir) = setup_test_db().await?;
+            let other_conn = libsql::Builder::new_local(temp_dir.path().join("test.db"))
+                .build()
+                .await?
+                .connect()?;
+
+            let (first, second) = tokio::join!(
+                migrate(&conn, migration_dir.clone()),
+                migrate(&other_conn, migration_dir.clone())
+            );
+            let (first, second) = (first?, second?);
+
+            let mut applied = [first.applied, second.applied].concat();
+            applied.sort();
+            assert_eq!(applied.len(), 5);
+            applied.dedup();
+            assert_eq!(applied.len(), 5);
+
+            Ok(())
+        }
+    }
+
+    mod create {
+        use libsql_migration::{
+            dir::{
+                CreateMigrationOptions, MigrationPrefix, create_migration,
+                create_migration_with_options,
+            },
+            errors::LibsqlDirMigratorError,
+        };
+
+        use super::super::*;
+        use crate::migration_tests::setup_test_db;
+
+        #[tokio::test]
+        async fn continues_sequence() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+
+            let options = CreateMigrationOptions {
+                prefix: MigrationPrefix::Sequence,
+                reversible: true,
+                ..Default::default()
+            };
+            let created = create_migration_with_options(
+                Some(&conn),
+                migration_dir.clone(),
+                "Add users!",
+                &options,
+            )
+            .await?;
+
+            assert_eq!(
+                created,
+                vec![
+                    migration_dir.join("0005_add_users.up.sql"),
+                    migration_dir.join("0005_add_users.down.sql")
+                ]
+            );
+            assert!(created.iter().all(|path| path.is_file()));
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn prefixes_with_timestamp() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+            migrate(&conn, migration_dir.clone()).await?;
+
+            let created = create_migration(&conn, migration_dir.clone(), "add email").await?;
+            assert_eq!(created.len(), 1);
+
+            let file_name = created[0]
+                .file_name()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string();
+            let (prefix, rest) = file_name.split_at(14);
+            assert!(prefix.chars().all(|c| c.is_ascii_digit()));
+            assert!(prefix.starts_with("20"));
+            assert_eq!(rest, "_add_email.sql");
+
+            std::fs::write(&created[0], "ALTER TABLE test1 ADD name TEXT;")?;
+            let report = migrate(&conn, migration_dir).await?;
+            assert_eq!(report.applied, vec![file_name]);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn refuses_id_before_applied() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+            std::fs::write(
+                migration_dir.join("99990101000000_future.sql"),
+                "CREATE TABLE future (id INTEGER);",
+            )?;
+            migrate(&conn, migration_dir.clone()).await?;
+
+            match create_migration(&conn, migration_dir.clone(), "add email").await {
+                Err(LibsqlDirMigratorError::MigrationSortsBeforeApplied { applied_id, .. }) => {
+                    assert_eq!(applied_id, "99990101000000_future.sql");
+                }
+                other => {
+                    return Err(
+                        format!("Expected MigrationSortsBeforeApplied, got {:?}", other).into(),
+                    );
+                }
+            }
+            assert_eq!(std::fs::read_dir(&migration_dir)?.count(), 6);
+
+            let created = create_migration_with_options(
+                None,
+                migration_dir,
+                "add email",
+                &CreateMigrationOptions::default(),
+            )
+            .await?;
+            assert_eq!(created.len(), 1);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn rejects_empty_description() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+
+            assert!(matches!(
+                create_migration(&conn, migration_dir, " -- ").await,
+                Err(LibsqlDirMigratorError::InvalidMigrationName(_))
+            ));
+
+            Ok(())
+        }
+    }
+
+    mod ordering {
+        use libsql_migration::{
+            dir::migrate_with_options,
+            errors::{LibsqlDirMigratorError, LibsqlMigratorBaseError},
+            util::{MigrateOptions, OrderingPolicy},
+        };
+
+        use super::super::*;
+        use crate::migration_tests::setup_test_db;
+
+        /// Applies the base migrations, then adds two files sorting before
+        /// `0004_test4.sql` and one sorting after it.
+        async fn setup_late_files()
+        -> Result<(libsql::Connection, tempfile::TempDir, PathBuf), Box<dyn std::error::Error>>
+        {
+            let (conn, temp_dir, migration_dir) = setup_test_db().await?;
+            migrate(&conn, migration_dir.clone()).await?;
+
+            std::fs::write(
+                migration_dir.join("0002_late.sql"),
+                "CREATE TABLE late2 (id INTEGER);",
+            )?;
+            std::fs::write(
+                migration_dir.join("0003_late.sql"),
+                "CREATE TABLE late3 (id INTEGER);",
+            )?;
+            std::fs::write(
+                migration_dir.join("0005_next.sql"),
+                "CREATE TABLE next (id INTEGER);",
+            )?;
+
+            Ok((conn, temp_dir, migration_dir))
+        }
+
+        #[tokio::test]
+        async fn strict_rejects_out_of_order() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_late_files().await?;
+            let options = MigrateOptions {
+                ordering: OrderingPolicy::Strict,
+                ..Default::default()
+            };
+
+            match migrate_with_options(&conn, migration_dir.clone(), &options).await {
+                Err(LibsqlDirMigratorError::BaseError(
+                    LibsqlMigratorBaseError::OutOfOrderMigrations(ids),
+                )) => assert_eq!(
+                    ids,
+                    vec!["0002_late.sql".to_string(), "0003_late.sql".to_string()]
+                ),
+                other => {
+                    return Err(format!("Expected OutOfOrderMigrations, got {:?}", other).into());
+                }
+            }
+
+            let mut rows = conn
+                .query(
+                    "SELECT name FROM sqlite_master WHERE name IN ('late2', 'next');",
+                    libsql::params![],
+                )
+                .await?;
+            assert!(rows.next().await?.is_none());
+
+            let dry_run = MigrateOptions {
+                dry_run: true,
+                ..options
+            };
+            assert!(
+                migrate_with_options(&conn, migration_dir, &dry_run)
+                    .await
+                    .is_err()
+            );
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn warn_applies_and_reports() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_late_files().await?;
+            let options = MigrateOptions {
+                ordering: OrderingPolicy::Warn,
+                ..Default::default()
+            };
+
+            let report = migrate_with_options(&conn, migration_dir, &options).await?;
+            assert_eq!(report.applied.len(), 3);
+            assert_eq!(
+                report.out_of_order,
+                vec!["0002_late.sql".to_string(), "0003_late.sql".to_string()]
+            );
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn allow_applies_silently() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_late_files().await?;
+
+            let report = migrate(&conn, migration_dir).await?;
+            assert_eq!(report.applied.len(), 3);
+            assert!(report.out_of_order.is_empty());
+
+            Ok(())
+        }
+    }
+
+    mod strategy {
+        use libsql_migration::{
+            dir::{DirSource, migrate_with_options},
+            errors::{LibsqlDirMigratorError, LibsqlMigratorBaseError},
+            migrator::{CodeMigrations, MigrationSource, Migrator},
+            util::{MigrateOptions, OrderingPolicy, OrderingStrategy},
+        };
+
+        use crate::migration_tests::setup_test_db;
+
+        fn options(strategy: OrderingStrategy) -> MigrateOptions {
+            MigrateOptions {
+                strategy,
+                ..Default::default()
+            }
+        }
+
+        fn write_files(
+            dir: &std::path::Path,
+            files: &[(&str, &str)],
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            std::fs::create_dir_all(dir)?;
+            for (name, script) in files {
+                std::fs::write(dir.join(name), script)?;
+            }
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn numeric_prefix() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, temp_dir, _) = setup_test_db().await?;
+            let migration_dir = temp_dir.path().join("numeric");
+            write_files(
+                &migration_dir,
+                &[
+                    ("9_users.sql", "CREATE TABLE users (id INTEGER);"),
+                    ("10_email.sql", "ALTER TABLE users ADD email TEXT;"),
+                ],
+            )?;
+
+            let report = migrate_with_options(
+                &conn,
+                migration_dir,
+                &options(OrderingStrategy::NumericPrefix),
+            )
+            .await?;
+            assert_eq!(
+                report.applied,
+                vec!["9_users.sql".to_string(), "10_email.sql".to_string()]
+            );
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn version_prefix() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, temp_dir, _) = setup_test_db().await?;
+            let migration_dir = temp_dir.path().join("version");
+            write_files(
+                &migration_dir,
+                &[
+                    ("v1.10_name.sql", "ALTER TABLE users ADD name TEXT;"),
+                    ("V1.9.1_email.sql", "ALTER TABLE users ADD email TEXT;"),
+                    ("v1_users.sql", "CREATE TABLE users (id INTEGER);"),
+                ],
+            )?;
+
+            let report = migrate_with_options(
+                &conn,
+                migration_dir.clone(),
+                &options(OrderingStrategy::VersionPrefix),
+            )
+            .await?;
+            assert_eq!(
+                report.applied,
+                vec![
+                    "v1_users.sql".to_string(),
+                    "V1.9.1_email.sql".to_string(),
+                    "v1.10_name.sql".to_string()
+                ]
+            );
+
+            write_files(&migration_dir, &[("v1.10.0_other.sql", "SELECT 1;")])?;
+            match migrate_with_options(
+                &conn,
+                migration_dir,
+                &options(OrderingStrategy::VersionPrefix),
+            )
+            .await
+            {
+                Err(LibsqlDirMigratorError::BaseError(
+                    LibsqlMigratorBaseError::DuplicateMigrationVersion(_, _),
+                )) => {}
+                other => {
+                    return Err(
+                        format!("Expected DuplicateMigrationVersion, got {:?}", other).into(),
+                    );
+                }
+            }
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn rejects_duplicate_version() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+
+            match migrate_with_options(
+                &conn,
+                migration_dir,
+                &options(OrderingStrategy::NumericPrefix),
+            )
+            .await
+            {
+                Err(LibsqlDirMigratorError::BaseError(
+                    LibsqlMigratorBaseError::DuplicateMigrationVersion(first, second),
+                )) => {
+                    assert_eq!(first, "test/0001_test0.sql");
+                    assert_eq!(second, "0001_test1.sql");
+                }
+                other => {
+                    return Err(
+                        format!("Expected DuplicateMigrationVersion, got {:?}", other).into(),
+                    );
+                }
+            }
+
+            let mut rows = conn
+                .query(
+                    "SELECT name FROM sqlite_master WHERE name = 'test1';",
+                    libsql::params![],
+                )
+                .await?;
+            assert!(rows.next().await?.is_none());
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn timestamp_prefix() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, temp_dir, _) = setup_test_db().await?;
+            let migration_dir = temp_dir.path().join("timestamp");
+            write_files(
+                &migration_dir,
+                &[
+                    (
+                        "20261017093000_users.sql",
+                        "CREATE TABLE users (id INTEGER);",
+                    ),
+                    ("202610_email.sql", "ALTER TABLE users ADD email TEXT;"),
+                ],
+            )?;
+
+            match migrate_with_options(
+                &conn,
+                migration_dir,
+                &options(OrderingStrategy::TimestampPrefix),
+            )
+            .await
+            {
+                Err(LibsqlDirMigratorError::BaseError(
+                    LibsqlMigratorBaseError::InvalidMigrationVersion(id),
+                )) => assert_eq!(id, "202610_email.sql"),
+                other => {
+                    return Err(format!("Expected InvalidMigrationVersion, got {:?}", other).into());
+                }
+            }
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn merged_sources_follow_strategy() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, temp_dir, _) = setup_test_db().await?;
+            let migration_dir = temp_dir.path().join("merged");
+            write_files(
+                &migration_dir,
+                &[
+                    ("9_users.sql", "CREATE TABLE users (id INTEGER);"),
+                    ("10_email.sql", "ALTER TABLE users ADD email TEXT;"),
+                    ("30_index.sql", "CREATE INDEX idx_name ON users (name);"),
+                ],
+            )?;
+
+            let source =
+                DirSource::new(migration_dir).merge(CodeMigrations::new().add("20_name", |conn| {
+                    Box::pin(async move {
+                        conn.execute("ALTER TABLE users ADD name TEXT;", ()).await?;
+                        Ok(())
+                    })
+                }));
+
+            let report = Migrator::new(source)
+                .with_options(options(OrderingStrategy::NumericPrefix))
+                .migrate(&conn)
+                .await?;
+            assert_eq!(
+                report.applied,
+                vec!["9_users.sql", "10_email.sql", "20_name", "30_index.sql"]
+            );
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn strict_policy_uses_strategy() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, temp_dir, _) = setup_test_db().await?;
+            let migration_dir = temp_dir.path().join("strict");
+            write_files(
+                &migration_dir,
+                &[("10_users.sql", "CREATE TABLE users (id INTEGER);")],
+            )?;
+            let migrator =
+                Migrator::new(DirSource::new(migration_dir.clone())).with_options(MigrateOptions {
+                    ordering: OrderingPolicy::Strict,
+                    ..options(OrderingStrategy::NumericPrefix)
+                });
+
+            migrator.migrate(&conn).await?;
+
+            write_files(&migration_dir, &[("9_email.sql", "SELECT 1;")])?;
+            match migrator.migrate(&conn).await {
+                Err(LibsqlDirMigratorError::BaseError(
+                    LibsqlMigratorBaseError::OutOfOrderMigrations(ids),
+                )) => assert_eq!(ids, vec!["9_email.sql"]),
+                other => {
+                    return Err(format!("Expected OutOfOrderMigrations, got {:?}", other).into());
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    mod nested {
+        use libsql_migration::{
+            dir::migrate_with_options,
+            errors::LibsqlDirMigratorError,
+            util::{MigrateOptions, NestedDirMode},
+        };
+
+        use super::super::*;
+        use crate::migration_tests::setup_test_db;
+
+        fn flat() -> MigrateOptions {
+            MigrateOptions {
+                nested_dirs: NestedDirMode::Flat,
+                ..Default::default()
+            }
+        }
+
+        fn write_nested(dir: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
+            std::fs::create_dir_all(dir.join("a"))?;
+            std::fs::create_dir_all(dir.join("b"))?;
+            std::fs::write(dir.join("b/0001.sql"), "CREATE TABLE b (id INTEGER);")?;
+            std::fs::write(dir.join("a/0001.sql"), "CREATE TABLE a (id INTEGER);")?;
+            std::fs::write(dir.join("0002.sql"), "ALTER TABLE a ADD name TEXT;")?;
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn namespaced_ids_are_paths() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, temp_dir, _) = setup_test_db().await?;
+            let migration_dir = temp_dir.path().join("nested");
+            write_nested(&migration_dir)?;
+
+            let report = migrate(&conn, migration_dir).await?;
+            assert_eq!(
+                report.applied,
+                vec![
+                    "a/0001.sql".to_string(),
+                    "b/0001.sql".to_string(),
+                    "0002.sql".to_string()
+                ]
+            );
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn flat_ids_are_file_names() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir, migration_dir) = setup_test_db().await?;
+
+            let report = migrate_with_options(&conn, migration_dir, &flat()).await?;
+            assert_eq!(report.applied.len(), 5);
+            assert!(report.applied.contains(&"0001_test0.sql".to_string()));
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn flat_rejects_conflicting_files() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, temp_dir, _) = setup_test_db().await?;
+            let migration_dir = temp_dir.path().join("nested");
+            write_nested(&migration_dir)?;
+            std::fs::create_dir_all(migration_dir.join("c"))?;
+            std::fs::write(migration_dir.join("c/0002.sql"), "SELECT 1;")?;
+
+            match migrate_with_options(&conn, migration_dir, &flat()).await {
+                Err(LibsqlDirMigratorError::ConflictingMigrationFiles(paths)) => assert_eq!(
+                    paths,
+                    vec![
+                        "a/0001.sql".to_string(),
+                        "b/0001.sql".to_string(),
+                        "0002.sql".to_string(),
+                        "c/0002.sql".to_string()
+                    ]
+                ),
+                other => {
+                    return Err(
+                        format!("Expected ConflictingMigrationFiles, got {:?}", other).into(),
+                    );
+                }
+            }
+
+            let mut rows = conn
+                .query(
+                    "SELECT name FROM sqlite_master WHERE name IN ('a', 'b');",
+                    libsql::params![],
+                )
+                .await?;
+            assert!(rows.next().await?.is_none());
+
+            Ok(())
+        }
     }
 }
@@ -0,0 +1 @@
+ALTER TABLE users ADD email TEXT;
@@ -0,0 +1,127 @@
+#![cfg(feature = "embed")]
+
+use libsql_migration::embed::{EmbeddedMigrations, migrate};
+use libsql_migration::embed_migrations;
+use tempfile::tempdir;
+
+static MIGRATIONS: EmbeddedMigrations = embed_migrations!("tests/embed-sql");
+
+#[cfg(test)]
+mod migration_tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    async fn setup_test_db() -> Result<(libsql::Connection, TempDir), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db");
+        let db = libsql::Builder::new_local(db_path).build().await?;
+        let conn = db.connect()?;
+
+        Ok((conn, temp_dir))
+    }
+
+    mod migration {
+        use libsql_migration::embed::{plan, rollback, status};
+        use libsql_migration::util::MigrationState;
+
+        use crate::migration_tests::setup_test_db;
+
+        use super::super::*;
+
+        #[test]
+        fn embeds_every_sql_file() {
+            let mut files: Vec<&str> = MIGRATIONS.files().collect();
+            files.sort();
+
+            assert_eq!(
+                files,
+                vec![
+                    "0001_users.down.sql",
+                    "0001_users.up.sql",
+                    "0003_email.sql",
+                    "nested/0002_posts.sql"
+                ]
+            );
+        }
+
+        #[tokio::test]
+        async fn applies_in_file_name_order() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+
+            let report = migrate(&conn, &MIGRATIONS).await?;
+            assert_eq!(
+                report.applied,
+                vec![
+                    "0001_users.up.sql".to_string(),
+                    "nested/0002_posts.sql".to_string(),
+                    "0003_email.sql".to_string()
+                ]
+            );
+
+            conn.execute(
+                "INSERT INTO users (id, email) VALUES (1, 'a@example.com');",
+                libsql::params![],
+            )
+            .await?;
+
+            let report = migrate(&conn, &MIGRATIONS).await?;
+            assert!(report.applied.is_empty());
+            assert_eq!(report.skipped.len(), 3);
+
+            assert!(
+                status(&conn, &MIGRATIONS)
+                    .await?
+                    .iter()
+                    .all(|migration| migration.state == MigrationState::Applied)
+            );
+            assert!(plan(&conn, &MIGRATIONS).await?.is_empty());
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn stores_down_scripts() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+
+            migrate(&conn, &MIGRATIONS).await?;
+
+            conn.execute("DROP TABLE posts;", libsql::params![]).await?;
+            conn.execute(
+                "DELETE FROM libsql_migrations WHERE id != '0001_users.up.sql';",
+                libsql::params![],
+            )
+            .await?;
+
+            let reverted = rollback(&conn, None).await?;
+            assert_eq!(reverted, vec!["0001_users.up.sql".to_string()]);
+
+            let mut rows = conn
+                .query(
+                    "SELECT name FROM sqlite_master WHERE name = 'users';",
+                    libsql::params![],
+                )
+                .await?;
+            assert!(rows.next().await?.is_none());
+
+            Ok(())
+        }
+
+        #[cfg(feature = "dir")]
+        #[tokio::test]
+        async fn matches_dir_migrations() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+
+            let from_dir = libsql_migration::dir::plan(
+                &conn,
+                std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/embed-sql"),
+            )
+            .await?;
+            let embedded = plan(&conn, &MIGRATIONS).await?;
+
+            assert_eq!(from_dir, embedded);
+
+            Ok(())
+        }
+    }
+}
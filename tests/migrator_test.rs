@@ -0,0 +1,343 @@
+use libsql_migration::errors::LibsqlMigratorBaseError;
+use libsql_migration::migrator::{MigrationSource, Migrator};
+use libsql_migration::util::Migration;
+use tempfile::tempdir;
+
+#[cfg(test)]
+mod migration_tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    async fn setup_test_db() -> Result<(libsql::Connection, TempDir), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db");
+        let db = libsql::Builder::new_local(db_path).build().await?;
+        let conn = db.connect()?;
+
+        Ok((conn, temp_dir))
+    }
+
+    /// Source keeping its scripts in memory, standing in for a user defined store.
+    struct ScriptStore {
+        scripts: Vec<(&'static str, &'static str, Option<&'static str>)>,
+        available: bool,
+    }
+
+    #[derive(Debug)]
+    enum ScriptStoreError {
+        Unavailable,
+        Migration(LibsqlMigratorBaseError),
+    }
+
+    impl From<LibsqlMigratorBaseError> for ScriptStoreError {
+        fn from(value: LibsqlMigratorBaseError) -> Self {
+            ScriptStoreError::Migration(value)
+        }
+    }
+
+    impl MigrationSource for ScriptStore {
+        type Error = ScriptStoreError;
+
+        async fn migrations(&self) -> Result<Vec<Migration>, Self::Error> {
+            if !self.available {
+                return Err(ScriptStoreError::Unavailable);
+            }
+
+            Ok(self
+                .scripts
+                .iter()
+                .map(|(id, script, down)| {
+                    Migration::new(id.to_string(), script.to_string(), down.map(str::to_string))
+                })
+                .collect())
+        }
+    }
+
+    fn store() -> ScriptStore {
+        ScriptStore {
+            scripts: vec![
+                (
+                    "0001_users",
+                    "CREATE TABLE users (id INTEGER PRIMARY KEY);",
+                    Some("DROP TABLE users;"),
+                ),
+                (
+                    "0002_email",
+                    "ALTER TABLE users ADD email TEXT;",
+                    Some("ALTER TABLE users DROP COLUMN email;"),
+                ),
+            ],
+            available: true,
+        }
+    }
+
+    mod custom_source {
+        use libsql_migration::util::MigrationState;
+
+        use super::*;
+
+        #[tokio::test]
+        async fn applies_and_tracks_custom_source() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+            let migrator = Migrator::new(store());
+
+            let report = migrator.migrate(&conn).await.unwrap();
+            assert_eq!(
+                report.applied,
+                vec!["0001_users".to_string(), "0002_email".to_string()]
+            );
+
+            let report = migrator.migrate(&conn).await.unwrap();
+            assert_eq!(report.skipped.len(), 2);
+
+            let statuses = migrator.status(&conn).await.unwrap();
+            assert!(
+                statuses
+                    .iter()
+                    .all(|status| status.state == MigrationState::Applied)
+            );
+            migrator.verify(&conn).await.unwrap();
+
+            let reverted = migrator
+                .rollback(&conn, Some("0001_users".to_string()))
+                .await
+                .unwrap();
+            assert_eq!(reverted, vec!["0002_email".to_string()]);
+            assert_eq!(migrator.plan(&conn).await.unwrap().len(), 1);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn propagates_source_errors() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+
+            let migrator = Migrator::new(ScriptStore {
+                available: false,
+                ..store()
+            });
+
+            assert!(matches!(
+                migrator.migrate(&conn).await,
+                Err(ScriptStoreError::Unavailable)
+            ));
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn rejects_duplicate_ids() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+
+            let mut source = store();
+            source
+                .scripts
+                .push(("0001_users", "CREATE TABLE other (id INTEGER);", None));
+
+            match Migrator::new(source).migrate(&conn).await {
+                Err(ScriptStoreError::Migration(
+                    LibsqlMigratorBaseError::DuplicateMigrationId(id),
+                )) => assert_eq!(id, "0001_users"),
+                other => {
+                    return Err(format!("Expected DuplicateMigrationId, got {:?}", other).into());
+                }
+            }
+
+            let mut rows = conn
+                .query(
+                    "SELECT name FROM sqlite_master WHERE name = 'users';",
+                    libsql::params![],
+                )
+                .await?;
+            assert!(rows.next().await?.is_none());
+
+            Ok(())
+        }
+
+        /// Source listing every id of `ids` without its script.
+        struct Unloaded(Vec<&'static str>);
+
+        impl MigrationSource for Unloaded {
+            type Error = LibsqlMigratorBaseError;
+
+            async fn migrations(&self) -> Result<Vec<Migration>, Self::Error> {
+                Ok(self.0.iter().map(|id| Migration::applied(*id)).collect())
+            }
+        }
+
+        #[tokio::test]
+        async fn skips_unloaded_applied_migrations() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+            Migrator::new(store()).migrate(&conn).await.unwrap();
+
+            let report = Migrator::new(Unloaded(vec!["0001_users", "0002_email"]))
+                .migrate(&conn)
+                .await?;
+            assert!(report.applied.is_empty());
+            assert_eq!(report.skipped, vec!["0001_users", "0002_email"]);
+
+            match Migrator::new(Unloaded(vec!["0001_users", "0003_index"]))
+                .migrate(&conn)
+                .await
+            {
+                Err(LibsqlMigratorBaseError::MigrationNotLoaded(id)) => {
+                    assert_eq!(id, "0003_index")
+                }
+                other => {
+                    return Err(format!("Expected MigrationNotLoaded, got {:?}", other).into());
+                }
+            }
+
+            Ok(())
+        }
+    }
+    #[cfg(feature = "dir")]
+    mod code {
+        use libsql_migration::{
+            dir::DirSource,
+            migrator::CodeMigrations,
+            util::{MigrateOptions, MigrationState},
+        };
+
+        use super::*;
+
+        fn backfill() -> CodeMigrations {
+            CodeMigrations::new().add("0002_backfill", |conn| {
+                Box::pin(async move {
+                    let mut rows = conn.query("SELECT id FROM users;", ()).await?;
+                    let mut ids: Vec<i64> = vec![];
+                    while let Some(row) = rows.next().await? {
+                        ids.push(row.get(0)?);
+                    }
+
+                    for id in ids {
+                        conn.execute(
+                            "UPDATE users SET name = ? WHERE id = ?;",
+                            libsql::params![format!("user-{}", id), id],
+                        )
+                        .await?;
+                    }
+                    Ok(())
+                })
+            })
+        }
+
+        #[tokio::test]
+        async fn interleaves_with_sql_migrations() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, temp_dir) = setup_test_db().await?;
+
+            let migration_dir = temp_dir.path().join("migrations");
+            std::fs::create_dir_all(&migration_dir)?;
+            std::fs::write(
+                migration_dir.join("0001_users.sql"),
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);
+INSERT INTO users (id) VALUES (1), (2);",
+            )?;
+            std::fs::write(
+                migration_dir.join("0003_not_null.sql"),
+                "CREATE TABLE named (name TEXT NOT NULL);
+INSERT INTO named SELECT name FROM users;",
+            )?;
+
+            let migrator = Migrator::new(DirSource::new(&migration_dir).merge(backfill()));
+
+            let report = migrator.migrate(&conn).await?;
+            assert_eq!(
+                report.applied,
+                vec![
+                    "0001_users.sql".to_string(),
+                    "0002_backfill".to_string(),
+                    "0003_not_null.sql".to_string()
+                ]
+            );
+
+            let mut rows = conn
+                .query("SELECT name FROM named ORDER BY name;", libsql::params![])
+                .await?;
+            assert_eq!(rows.next().await?.unwrap().get::<String>(0)?, "user-1");
+
+            let statuses = migrator.status(&conn).await?;
+            assert!(
+                statuses
+                    .iter()
+                    .all(|status| status.state == MigrationState::Applied)
+            );
+            assert_eq!(migrator.migrate(&conn).await?.skipped.len(), 3);
+
+            // Code has no script to checksum
+            let backfill = statuses
+                .iter()
+                .find(|status| status.id == "0002_backfill")
+                .unwrap();
+            assert_eq!(backfill.checksum, None);
+            let mut rows = conn
+                .query(
+                    "SELECT checksum FROM libsql_migrations WHERE id = '0002_backfill';",
+                    libsql::params![],
+                )
+                .await?;
+            assert_eq!(rows.next().await?.unwrap().get::<Option<String>>(0)?, None);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn failed_code_migration_is_rolled_back() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+
+            let source = CodeMigrations::new()
+                .add("0001_users", |conn| {
+                    Box::pin(async move {
+                        conn.execute("CREATE TABLE users (id INTEGER PRIMARY KEY);", ())
+                            .await?;
+                        Ok(())
+                    })
+                })
+                .add("0002_broken", |conn| {
+                    Box::pin(async move {
+                        conn.execute("INSERT INTO users (id) VALUES (1);", ())
+                            .await?;
+                        Err("backfill source unavailable".into())
+                    })
+                });
+
+            match Migrator::new(source)
+                .with_options(MigrateOptions::default())
+                .migrate(&conn)
+                .await
+            {
+                Err(LibsqlMigratorBaseError::CodeMigrationFailed {
+                    migration_id,
+                    source,
+                }) => {
+                    assert_eq!(migration_id, "0002_broken");
+                    assert_eq!(source.to_string(), "backfill source unavailable");
+                }
+                other => {
+                    return Err(format!("Expected CodeMigrationFailed, got {:?}", other).into());
+                }
+            }
+
+            let mut rows = conn
+                .query("SELECT COUNT(*) FROM users;", libsql::params![])
+                .await?;
+            assert_eq!(rows.next().await?.unwrap().get::<i64>(0)?, 0);
+
+            let mut rows = conn
+                .query(
+                    "SELECT status, error_message FROM libsql_migrations WHERE id = '0002_broken';",
+                    libsql::params![],
+                )
+                .await?;
+            let row = rows.next().await?.expect("Failure was not recorded");
+            assert_eq!(row.get::<i64>(0)?, 0);
+            assert!(
+                row.get::<String>(1)?
+                    .contains("backfill source unavailable")
+            );
+
+            Ok(())
+        }
+    }
+}
@@ -1,9 +1,15 @@
+#![cfg(feature = "remote")]
+
 use libsql_migration::remote::migrate;
 
+mod common;
+
 #[cfg(test)]
 mod migration_tests {
     use tempfile::tempdir;
 
+    use crate::common::TestServer;
+
     async fn setup_test_db()
     -> Result<(libsql::Connection, tempfile::TempDir), Box<dyn std::error::Error>> {
         let temp_dir = tempdir()?;
@@ -14,6 +20,37 @@ mod migration_tests {
         Ok((conn, temp_dir))
     }
 
+    /// Serves `/manifest.json` listing `/0001.sql` (with `/0001.down.sql`) and
+    /// `/0002.sql`.
+    fn start_server() -> TestServer {
+        let server = TestServer::start();
+        let manifest = format!(
+            r#"[
+  {{ "id": "0001_users", "url": "{}", "down_url": "{}" }},
+  {{ "id": "0002_email", "url": "{}" }}
+]"#,
+            server.url("/0001.sql"),
+            server.url("/0001.down.sql"),
+            server.url("/0002.sql"),
+        );
+
+        server
+            .route("/manifest.json", vec![(200, manifest)])
+            .route(
+                "/0001.sql",
+                vec![(200, "CREATE TABLE users (id INTEGER);".to_string())],
+            )
+            .route(
+                "/0001.down.sql",
+                vec![(200, "DROP TABLE users;".to_string())],
+            )
+            .route(
+                "/0002.sql",
+                vec![(200, "ALTER TABLE users ADD email TEXT;".to_string())],
+            );
+        server
+    }
+
     mod base {
         use libsql_migration::errors::LibsqlRemoteMigratorError;
 
@@ -264,4 +301,625 @@ mod migration_tests {
             Ok(())
         }
     }
+
+    mod auth {
+        use libsql_migration::{migrator::Migrator, remote::RemoteSource};
+        use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+        use crate::migration_tests::{setup_test_db, start_server};
+
+        #[tokio::test]
+        async fn sends_headers_with_every_request() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+            let server = start_server();
+
+            let source = RemoteSource::new(server.url("/manifest.json"))
+                .with_bearer_token("secret")
+                .with_header(
+                    HeaderName::from_static("x-api-key"),
+                    HeaderValue::from_static("key"),
+                );
+            let report = Migrator::new(source).migrate(&conn).await?;
+            assert_eq!(report.applied.len(), 2);
+
+            let requests = server.requests();
+            let mut paths: Vec<&str> = requests.iter().map(|r| r.path.as_str()).collect();
+            paths.sort();
+            assert_eq!(
+                paths,
+                vec!["/0001.down.sql", "/0001.sql", "/0002.sql", "/manifest.json"]
+            );
+            for request in requests {
+                assert_eq!(request.headers["authorization"], "Bearer secret");
+                assert_eq!(request.headers["x-api-key"], "key");
+            }
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn sends_basic_auth() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+            let server = start_server();
+
+            let source = RemoteSource::new(server.url("/manifest.json"))
+                .with_basic_auth("user", Some("pass"));
+            Migrator::new(source).migrate(&conn).await?;
+
+            assert!(
+                server
+                    .requests()
+                    .iter()
+                    .all(|request| request.headers["authorization"] == "Basic dXNlcjpwYXNz")
+            );
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn uses_custom_client() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+            let server = start_server();
+
+            let mut headers = HeaderMap::new();
+            headers.insert("x-client", HeaderValue::from_static("custom"));
+            let client = reqwest::Client::builder()
+                .default_headers(headers)
+                .build()?;
+
+            let source = RemoteSource::new(server.url("/manifest.json")).with_client(client);
+            Migrator::new(source).migrate(&conn).await?;
+
+            assert!(
+                server
+                    .requests()
+                    .iter()
+                    .all(|request| request.headers["x-client"] == "custom")
+            );
+
+            Ok(())
+        }
+    }
+
+    mod retry {
+        use std::time::Duration;
+
+        use libsql_migration::{
+            errors::LibsqlRemoteMigratorError, migrator::Migrator, remote::RemoteSource,
+        };
+
+        use crate::{
+            common::table_exists,
+            migration_tests::{setup_test_db, start_server},
+        };
+
+        fn source(url: String) -> RemoteSource {
+            RemoteSource::new(url)
+                .with_backoff(Duration::from_millis(10), Duration::from_millis(20))
+        }
+
+        #[tokio::test]
+        async fn retries_server_errors() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+            let server = start_server();
+            server.route(
+                "/0002.sql",
+                vec![
+                    (502, "Bad Gateway".to_string()),
+                    (503, "Unavailable".to_string()),
+                    (200, "ALTER TABLE users ADD email TEXT;".to_string()),
+                ],
+            );
+
+            let report = Migrator::new(source(server.url("/manifest.json")))
+                .migrate(&conn)
+                .await?;
+            assert_eq!(report.applied.len(), 2);
+            assert_eq!(server.hits("/0002.sql"), 3);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn gives_up_after_retries() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+            let server = start_server();
+            server.route("/0002.sql", vec![(503, "Unavailable".to_string())]);
+
+            let result = Migrator::new(source(server.url("/manifest.json")).with_retries(2))
+                .migrate(&conn)
+                .await;
+            assert!(matches!(
+                result,
+                Err(LibsqlRemoteMigratorError::UnexpectedStatus { status: 503, .. })
+            ));
+            assert_eq!(server.hits("/0002.sql"), 3);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn error_page_is_not_executed() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+            let server = start_server();
+            server.route(
+                "/0002.sql",
+                vec![(404, "<html><body>Not Found</body></html>".to_string())],
+            );
+
+            match Migrator::new(source(server.url("/manifest.json")))
+                .migrate(&conn)
+                .await
+            {
+                Err(LibsqlRemoteMigratorError::UnexpectedStatus { url, status }) => {
+                    assert_eq!(url, server.url("/0002.sql"));
+                    assert_eq!(status, 404);
+                }
+                other => {
+                    return Err(format!("Expected UnexpectedStatus, got {:?}", other).into());
+                }
+            }
+            assert_eq!(server.hits("/0002.sql"), 1);
+            assert!(!table_exists(&conn, "users").await?);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn retries_timeouts() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+            let server = start_server();
+            let script = "CREATE TABLE users (id INTEGER);".to_string();
+            let source =
+                source(server.url("/manifest.json")).with_timeout(Some(Duration::from_millis(200)));
+
+            server.route_with_delays(
+                "/0001.sql",
+                vec![
+                    (200, script.clone(), Duration::from_secs(2)),
+                    (200, script.clone(), Duration::ZERO),
+                ],
+            );
+            let report = Migrator::new(source.clone()).migrate(&conn).await?;
+            assert_eq!(report.applied.len(), 2);
+            assert_eq!(server.hits("/0001.sql"), 2);
+
+            let (conn, _other_temp_dir) = setup_test_db().await?;
+            server.route_with_delays("/0001.sql", vec![(200, script, Duration::from_secs(2))]);
+            let result = Migrator::new(source.with_retries(0)).migrate(&conn).await;
+            assert!(matches!(
+                result,
+                Err(LibsqlRemoteMigratorError::ReqwestError(ref e)) if e.is_timeout()
+            ));
+
+            Ok(())
+        }
+    }
+
+    mod integrity {
+        use ed25519_dalek::{Signer, SigningKey};
+        use libsql_migration::{
+            errors::LibsqlRemoteMigratorError, migrator::Migrator, remote::RemoteSource,
+        };
+        use sha2::{Digest, Sha256};
+
+        use crate::{common::TestServer, migration_tests::setup_test_db};
+
+        const USERS: &str = "CREATE TABLE users (id INTEGER);";
+        const DROP_USERS: &str = "DROP TABLE users;";
+        const EMAIL: &str = "ALTER TABLE users ADD email TEXT;";
+
+        fn sha256(content: &str) -> String {
+            format!("{:x}", Sha256::digest(content.as_bytes()))
+        }
+
+        fn hex(bytes: &[u8]) -> String {
+            bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+        }
+
+        fn signing_key() -> SigningKey {
+            SigningKey::from_bytes(&[7; 32])
+        }
+
+        /// Serves the scripts and returns the server with a manifest listing them,
+        /// with `email_sha256` as the hash of `/0002.sql`.
+        fn start_server(email_sha256: Option<&str>) -> (TestServer, String) {
+            let server = TestServer::start();
+            server
+                .route("/0001.sql", vec![(200, USERS.to_string())])
+                .route("/0001.down.sql", vec![(200, DROP_USERS.to_string())])
+                .route("/0002.sql", vec![(200, EMAIL.to_string())]);
+
+            let email_sha256 = match email_sha256 {
+                Some(hash) => format!(r#", "sha256": "{}""#, hash),
+                None => String::new(),
+            };
+            let manifest = format!(
+                r#"[
+  {{ "id": "0001_users", "url": "{}", "sha256": "{}", "down_url": "{}", "down_sha256": "{}" }},
+  {{ "id": "0002_email", "url": "{}"{} }}
+]"#,
+                server.url("/0001.sql"),
+                sha256(USERS).to_uppercase(),
+                server.url("/0001.down.sql"),
+                sha256(DROP_USERS),
+                server.url("/0002.sql"),
+                email_sha256,
+            );
+
+            (server, manifest)
+        }
+
+        fn serve_signed(server: &TestServer, manifest: &str, signed: &str) {
+            let signature = signing_key().sign(signed.as_bytes());
+            server
+                .route("/manifest.json", vec![(200, manifest.to_string())])
+                .route(
+                    "/manifest.json.sig",
+                    vec![(200, hex(&signature.to_bytes()))],
+                );
+        }
+
+        fn signed_source(server: &TestServer) -> RemoteSource {
+            RemoteSource::new(server.url("/manifest.json"))
+                .with_manifest_key(signing_key().verifying_key())
+        }
+
+        #[tokio::test]
+        async fn checks_sha256_before_executing() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+            let (server, manifest) = start_server(Some(&sha256("SELECT 1;")));
+            server.route("/manifest.json", vec![(200, manifest)]);
+
+            match Migrator::new(RemoteSource::new(server.url("/manifest.json")))
+                .migrate(&conn)
+                .await
+            {
+                Err(LibsqlRemoteMigratorError::IntegrityMismatch {
+                    id,
+                    expected,
+                    actual,
+                    ..
+                }) => {
+                    assert_eq!(id, "0002_email");
+                    assert_eq!(expected, sha256("SELECT 1;"));
+                    assert_eq!(actual, sha256(EMAIL));
+                }
+                other => {
+                    return Err(format!("Expected IntegrityMismatch, got {:?}", other).into());
+                }
+            }
+
+            let mut rows = conn
+                .query(
+                    "SELECT name FROM sqlite_master WHERE name = 'users';",
+                    libsql::params![],
+                )
+                .await?;
+            assert!(rows.next().await?.is_none());
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn applies_signed_manifest() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+            let (server, manifest) = start_server(Some(&sha256(EMAIL)));
+            serve_signed(&server, &manifest, &manifest);
+
+            let report = Migrator::new(signed_source(&server)).migrate(&conn).await?;
+            assert_eq!(report.applied.len(), 2);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn rejects_tampered_manifest() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+            let (server, manifest) = start_server(Some(&sha256(EMAIL)));
+            let tampered = manifest.replace("0002.sql", "0001.sql");
+            serve_signed(&server, &tampered, &manifest);
+
+            assert!(matches!(
+                Migrator::new(signed_source(&server)).migrate(&conn).await,
+                Err(LibsqlRemoteMigratorError::InvalidManifestSignature { .. })
+            ));
+
+            server.route(
+                "/manifest.json.sig",
+                vec![(200, "not a signature".to_string())],
+            );
+            assert!(matches!(
+                Migrator::new(signed_source(&server)).migrate(&conn).await,
+                Err(LibsqlRemoteMigratorError::InvalidManifestSignature { .. })
+            ));
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn signed_manifest_requires_hashes() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+            let (server, manifest) = start_server(None);
+            serve_signed(&server, &manifest, &manifest);
+
+            match Migrator::new(signed_source(&server)).migrate(&conn).await {
+                Err(LibsqlRemoteMigratorError::MissingIntegrityHash(id)) => {
+                    assert_eq!(id, "0002_email")
+                }
+                other => {
+                    return Err(format!("Expected MissingIntegrityHash, got {:?}", other).into());
+                }
+            }
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn signed_manifest_accepts_inline_sql() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+            let server = TestServer::start();
+            let manifest = format!(r#"[{{ "id": "0001_users", "sql": "{}" }}]"#, USERS);
+            serve_signed(&server, &manifest, &manifest);
+
+            let report = Migrator::new(signed_source(&server)).migrate(&conn).await?;
+            assert_eq!(report.applied, vec!["0001_users"]);
+
+            Ok(())
+        }
+    }
+
+    mod manifest {
+        use libsql_migration::{
+            errors::LibsqlRemoteMigratorError, migrator::Migrator, remote, remote::RemoteSource,
+        };
+
+        use crate::{common::TestServer, migration_tests::setup_test_db};
+
+        #[tokio::test]
+        async fn resolves_relative_urls() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+            let server = TestServer::start();
+            server
+                .route(
+                    "/releases/v1/0001.sql",
+                    vec![(200, "CREATE TABLE users (id INTEGER);".to_string())],
+                )
+                .route(
+                    "/releases/shared/0002.sql",
+                    vec![(200, "ALTER TABLE users ADD email TEXT;".to_string())],
+                )
+                .route(
+                    "/0003.sql",
+                    vec![(200, "ALTER TABLE users ADD name TEXT;".to_string())],
+                )
+                .route(
+                    "/elsewhere/0004.sql",
+                    vec![(200, "CREATE INDEX idx_name ON users (name);".to_string())],
+                );
+            let manifest = format!(
+                r#"[
+  {{ "id": "0001", "url": "0001.sql" }},
+  {{ "id": "0002", "url": "../shared/0002.sql" }},
+  {{ "id": "0003", "url": "/0003.sql" }},
+  {{ "id": "0004", "url": "{}" }}
+]"#,
+                server.url("/elsewhere/0004.sql")
+            );
+            server.route("/releases/v1/manifest.json", vec![(200, manifest)]);
+
+            let report = Migrator::new(RemoteSource::new(server.url("/releases/v1/manifest.json")))
+                .migrate(&conn)
+                .await?;
+            assert_eq!(report.applied, vec!["0001", "0002", "0003", "0004"]);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn applies_inline_sql() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+            let server = TestServer::start();
+            server.route(
+                "/manifest.json",
+                vec![(
+                    200,
+                    r#"[
+  { "id": "0001_users", "sql": "CREATE TABLE users (id INTEGER);", "down_sql": "DROP TABLE users;" },
+  { "id": "0002_email", "sql": "ALTER TABLE users ADD email TEXT;", "down_sql": "ALTER TABLE users DROP email;" }
+]"#
+                    .to_string(),
+                )],
+            );
+
+            let report = Migrator::new(RemoteSource::new(server.url("/manifest.json")))
+                .migrate(&conn)
+                .await?;
+            assert_eq!(report.applied, vec!["0001_users", "0002_email"]);
+            assert_eq!(server.requests().len(), 1);
+
+            conn.execute("INSERT INTO users (email) VALUES ('a@b.c');", ())
+                .await?;
+
+            let reverted = remote::rollback(&conn, None).await?;
+            assert_eq!(reverted, vec!["0002_email", "0001_users"]);
+
+            let mut rows = conn
+                .query(
+                    "SELECT name FROM sqlite_master WHERE name = 'users';",
+                    libsql::params![],
+                )
+                .await?;
+            assert!(rows.next().await?.is_none());
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn sorts_ids_by_last_segment() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+            let server = TestServer::start();
+            server.route(
+                "/manifest.json",
+                vec![(
+                    200,
+                    r#"[
+  { "id": "users/0002_email", "sql": "ALTER TABLE users ADD email TEXT;" },
+  { "id": "users/0001_users", "sql": "CREATE TABLE users (id INTEGER);" },
+  { "id": "accounts/0003_accounts", "sql": "CREATE TABLE accounts (id INTEGER);" }
+]"#
+                    .to_string(),
+                )],
+            );
+
+            let report = Migrator::new(RemoteSource::new(server.url("/manifest.json")))
+                .migrate(&conn)
+                .await?;
+            assert_eq!(
+                report.applied,
+                vec![
+                    "users/0001_users",
+                    "users/0002_email",
+                    "accounts/0003_accounts"
+                ]
+            );
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn rejects_entries_without_a_single_script() -> Result<(), Box<dyn std::error::Error>>
+        {
+            let (conn, _temp_dir) = setup_test_db().await?;
+            let server = TestServer::start();
+            let source = RemoteSource::new(server.url("/manifest.json"));
+
+            for manifest in [
+                r#"[{ "id": "0001", "url": "0001.sql", "sql": "SELECT 1;" }]"#,
+                r#"[{ "id": "0001" }]"#,
+                r#"[{ "id": "0001", "sql": "SELECT 1;", "down_url": "0001.down.sql", "down_sql": "SELECT 1;" }]"#,
+            ] {
+                server.route("/manifest.json", vec![(200, manifest.to_string())]);
+
+                match Migrator::new(source.clone()).migrate(&conn).await {
+                    Err(LibsqlRemoteMigratorError::InvalidManifest(msg)) => {
+                        assert!(msg.contains("0001"))
+                    }
+                    other => {
+                        return Err(format!("Expected InvalidManifest, got {:?}", other).into());
+                    }
+                }
+            }
+            assert_eq!(server.requests().len(), 3);
+
+            Ok(())
+        }
+    }
+
+    mod prefetch {
+        use std::time::{Duration, Instant};
+
+        use libsql_migration::{
+            errors::LibsqlRemoteMigratorError, migrator::Migrator, remote::RemoteSource,
+        };
+
+        use crate::{common::TestServer, migration_tests::setup_test_db};
+
+        fn manifest(ids: &[&str]) -> String {
+            let entries: Vec<String> = ids
+                .iter()
+                .map(|id| format!(r#"{{ "id": "{}", "url": "{}.sql" }}"#, id, id))
+                .collect();
+            format!("[{}]", entries.join(", "))
+        }
+
+        #[tokio::test]
+        async fn skips_applied_scripts() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+            let server = TestServer::start();
+            server
+                .route(
+                    "/0001.sql",
+                    vec![(200, "CREATE TABLE users (id INTEGER);".to_string())],
+                )
+                .route(
+                    "/0002.sql",
+                    vec![(200, "ALTER TABLE users ADD email TEXT;".to_string())],
+                )
+                .route("/manifest.json", vec![(200, manifest(&["0001"]))]);
+            let source = RemoteSource::new(server.url("/manifest.json"));
+
+            Migrator::new(source.clone()).migrate(&conn).await?;
+
+            server.route("/manifest.json", vec![(200, manifest(&["0001", "0002"]))]);
+            let report = Migrator::new(source.clone()).migrate(&conn).await?;
+            assert_eq!(report.applied, vec!["0002"]);
+            assert_eq!(report.skipped, vec!["0001"]);
+            assert_eq!(server.hits("/0001.sql"), 1);
+            assert_eq!(server.hits("/0002.sql"), 1);
+
+            Migrator::new(source).verify(&conn).await?;
+            assert_eq!(server.hits("/0001.sql"), 2);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn failed_download_runs_nothing() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+            let server = TestServer::start();
+            server
+                .route(
+                    "/0001.sql",
+                    vec![(200, "CREATE TABLE users (id INTEGER);".to_string())],
+                )
+                .route("/manifest.json", vec![(200, manifest(&["0001", "0002"]))]);
+
+            assert!(matches!(
+                Migrator::new(RemoteSource::new(server.url("/manifest.json")).with_retries(0))
+                    .migrate(&conn)
+                    .await,
+                Err(LibsqlRemoteMigratorError::UnexpectedStatus { status: 404, .. })
+            ));
+
+            let mut rows = conn
+                .query(
+                    "SELECT name FROM sqlite_master WHERE name = 'users';",
+                    libsql::params![],
+                )
+                .await?;
+            assert!(rows.next().await?.is_none());
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn fetches_scripts_concurrently() -> Result<(), Box<dyn std::error::Error>> {
+            let (conn, _temp_dir) = setup_test_db().await?;
+            let server = TestServer::start();
+            let ids = ["0001", "0002", "0003", "0004"];
+            for id in ids {
+                server.route_with_delays(
+                    &format!("/{}.sql", id),
+                    vec![(
+                        200,
+                        format!("CREATE TABLE t{} (id INTEGER);", id),
+                        Duration::from_millis(500),
+                    )],
+                );
+            }
+            server.route("/manifest.json", vec![(200, manifest(&ids))]);
+
+            let started = Instant::now();
+            let report = Migrator::new(
+                RemoteSource::new(server.url("/manifest.json")).with_concurrency(ids.len()),
+            )
+            .migrate(&conn)
+            .await?;
+            assert_eq!(report.applied, ids);
+            assert!(started.elapsed() < Duration::from_millis(1500));
+
+            Ok(())
+        }
+    }
 }